@@ -0,0 +1,65 @@
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import bevy_pbr::mesh_functions
+
+struct ChunkMaterial {
+    tile_size: vec2<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> material: ChunkMaterial;
+@group(1) @binding(1)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var atlas_sampler: sampler;
+
+// the faces turned away from the sun still get this part of the light
+const AMBIENT_LIGHT: f32 = 0.5;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    // the position on the face, in blocks
+    @location(2) uv: vec2<f32>,
+    @location(3) tile_origin: vec2<f32>,
+    // the brightness of the light in front of the face, darkened by the ambient occlusion
+    @location(4) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) tile_origin: vec2<f32>,
+    @location(3) color: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = mesh_position_local_to_clip(mesh.model, vec4<f32>(vertex.position, 1.0));
+    out.world_normal = mesh_normal_local_to_world(vertex.normal);
+    out.uv = vertex.uv;
+    out.tile_origin = vertex.tile_origin;
+    out.color = vertex.color;
+    return out;
+}
+
+struct FragmentInput {
+    @location(0) world_normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) tile_origin: vec2<f32>,
+    @location(3) color: vec4<f32>,
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    // the tile is repeated once per block on the merged faces
+    let atlas_uv = in.tile_origin + fract(in.uv) * material.tile_size;
+    let color = textureSample(atlas_texture, atlas_sampler, atlas_uv);
+
+    let sun_direction = normalize(vec3<f32>(0.3, 1.0, 0.5));
+    let diffuse = max(dot(normalize(in.world_normal), sun_direction), 0.0);
+    let light = AMBIENT_LIGHT + (1.0 - AMBIENT_LIGHT) * diffuse;
+    return vec4<f32>(color.rgb * light * in.color.rgb, color.a);
+}
@@ -1,48 +1,144 @@
 use proc_macro::TokenStream;
-use proc_macro2;
 use quote::quote;
+use std::collections::HashSet;
 use syn::{self, braced, Ident};
 use syn::{
     parse::{Parse, ParseStream},
     parse_macro_input,
     punctuated::Punctuated,
-    Expr, LitBool, Result, Token,
+    Error, Expr, LitBool, LitFloat, LitInt, Result, Token,
 };
 
+const MAX_LIGHT_LEVEL: u8 = 15;
+const RENDER_LAYERS: [&str; 2] = ["Opaque", "Translucent"];
+const ORIENTATION_MODES: [&str; 3] = ["Fixed", "Axis", "Facing"];
+const PROPERTIES: [&str; 8] = [
+    "is_transparent",
+    "color",
+    "is_solid",
+    "is_fluid",
+    "light_level",
+    "hardness",
+    "render_layer",
+    "orientation_mode",
+];
+
+// every property is optional, the ones missing take their default value
+#[derive(Default)]
+struct BlockProperties {
+    is_transparent: Option<LitBool>,
+    color: Option<Expr>,
+    is_solid: Option<LitBool>,
+    is_fluid: Option<LitBool>,
+    light_level: Option<LitInt>,
+    hardness: Option<LitFloat>,
+    render_layer: Option<Ident>,
+    orientation_mode: Option<Ident>,
+}
+
 struct MyBlock {
     name: Ident,
-    is_transparent: LitBool,
-    color: Expr,
+    properties: BlockProperties,
 }
 
 impl Parse for MyBlock {
     fn parse(input: ParseStream<'_>) -> Result<MyBlock> {
-        mod kw {
-            ::syn::custom_keyword!(is_transparent);
-            ::syn::custom_keyword!(color);
-        }
         let name: Ident = input.parse()?;
         let _: Token![:] = input.parse()?;
 
         let content;
         braced!(content in input);
-        let _: kw::is_transparent = content.parse()?;
-        let _: Token![:] = content.parse()?;
-        let is_transparent: LitBool = content.parse()?;
-        let _: Token![,] = content.parse()?;
-        let _: kw::color = content.parse()?;
-        let _: Token![:] = content.parse()?;
-        let color: Expr = content.parse()?;
-        if content.peek(Token![,]) {
+
+        let mut properties = BlockProperties::default();
+        let mut parsed_properties = HashSet::new();
+
+        while !content.is_empty() {
+            let property: Ident = content.parse()?;
+            let _: Token![:] = content.parse()?;
+
+            let property_name = property.to_string();
+            if !parsed_properties.insert(property_name.clone()) {
+                let message = format!("the property `{}` is set twice", property_name);
+                return Err(Error::new(property.span(), message));
+            }
+
+            match property_name.as_str() {
+                "is_transparent" => properties.is_transparent = Some(content.parse()?),
+                "color" => properties.color = Some(content.parse()?),
+                "is_solid" => properties.is_solid = Some(content.parse()?),
+                "is_fluid" => properties.is_fluid = Some(content.parse()?),
+                "light_level" => properties.light_level = Some(parse_light_level(&content)?),
+                "hardness" => properties.hardness = Some(content.parse()?),
+                "render_layer" => properties.render_layer = Some(parse_render_layer(&content)?),
+                "orientation_mode" => {
+                    properties.orientation_mode = Some(parse_orientation_mode(&content)?)
+                }
+                _ => {
+                    let message = format!(
+                        "unknown block property `{}`, expected one of : {}",
+                        property_name,
+                        PROPERTIES.join(", ")
+                    );
+                    return Err(Error::new(property.span(), message));
+                }
+            }
+
+            if content.is_empty() {
+                break;
+            }
             let _: Token![,] = content.parse()?;
         }
 
-        Ok(Self {
-            name,
-            is_transparent,
-            color,
-        })
+        Ok(Self { name, properties })
+    }
+}
+
+fn parse_light_level(input: ParseStream<'_>) -> Result<LitInt> {
+    let light_level: LitInt = input.parse()?;
+    let value: u8 = light_level.base10_parse()?;
+    if value > MAX_LIGHT_LEVEL {
+        let message = format!("the light level must be between 0 and {}", MAX_LIGHT_LEVEL);
+        return Err(Error::new(light_level.span(), message));
     }
+    Ok(light_level)
+}
+
+fn parse_render_layer(input: ParseStream<'_>) -> Result<Ident> {
+    let render_layer: Ident = input.parse()?;
+    if !RENDER_LAYERS.contains(&render_layer.to_string().as_str()) {
+        let message = format!(
+            "unknown render layer `{}`, expected one of : {}",
+            render_layer,
+            RENDER_LAYERS.join(", ")
+        );
+        return Err(Error::new(render_layer.span(), message));
+    }
+    Ok(render_layer)
+}
+
+fn parse_orientation_mode(input: ParseStream<'_>) -> Result<Ident> {
+    let orientation_mode: Ident = input.parse()?;
+    if !ORIENTATION_MODES.contains(&orientation_mode.to_string().as_str()) {
+        let message = format!(
+            "unknown orientation mode `{}`, expected one of : {}",
+            orientation_mode,
+            ORIENTATION_MODES.join(", ")
+        );
+        return Err(Error::new(orientation_mode.span(), message));
+    }
+    Ok(orientation_mode)
+}
+
+fn check_duplicated_names(blocks: &Punctuated<MyBlock, Token![,]>) -> Result<()> {
+    let mut names = HashSet::new();
+    for block in blocks.iter() {
+        let name = &block.name;
+        if !names.insert(name.to_string()) {
+            let message = format!("the block `{}` is defined twice", name);
+            return Err(Error::new(name.span(), message));
+        }
+    }
+    Ok(())
 }
 
 #[proc_macro]
@@ -50,19 +146,50 @@ pub fn create_blocks(input: TokenStream) -> TokenStream {
     let blocks: Punctuated<MyBlock, Token![,]> =
         parse_macro_input!(input with Punctuated::parse_terminated);
 
+    if let Err(err) = check_duplicated_names(&blocks) {
+        return err.to_compile_error().into();
+    }
+
     let blocks_array = generate_blocks_array(&blocks);
     let block_id_function = generate_get_block_id_function(&blocks);
     let blocks_ids_mod = genereate_blocks_ids_module(&blocks);
+    let accessors = generate_accessors();
     let expanded = quote! {
+        #[derive(Clone, Copy, PartialEq, Eq, Debug, Default, serde::Deserialize)]
+        pub enum RenderLayer {
+            #[default]
+            Opaque,
+            Translucent,
+        }
+
+        // how the orientation of a block is chosen when it is placed :
+        // Fixed blocks ignore it, the top of the Axis blocks points away from
+        // the face they are placed against (like logs) and the front of the
+        // Facing blocks looks toward the player
+        #[derive(Clone, Copy, PartialEq, Eq, Debug, Default, serde::Deserialize)]
+        pub enum OrientationMode {
+            #[default]
+            Fixed,
+            Axis,
+            Facing,
+        }
+
         pub struct BlockInfos {
             pub id: u16,
             pub name: &'static str,
             pub is_transparent: bool,
             pub color: Option<Color>,
+            pub is_solid: bool,
+            pub is_fluid: bool,
+            pub light_level: u8,
+            pub hardness: f32,
+            pub render_layer: RenderLayer,
+            pub orientation_mode: OrientationMode,
         }
         #blocks_ids_mod
         #blocks_array
         #block_id_function
+        #accessors
     };
 
     expanded.into()
@@ -73,14 +200,51 @@ fn generate_blocks_array(blocks: &Punctuated<MyBlock, Token![,]>) -> proc_macro2
     for (i, block) in blocks.iter().enumerate() {
         let block_id = i as u16;
         let name = block.name.to_string().to_lowercase();
-        let is_transparent = block.is_transparent.clone();
-        let color = block.color.clone();
+        let is_transparent = match &block.properties.is_transparent {
+            Some(is_transparent) => quote! { #is_transparent },
+            None => quote! { false },
+        };
+        let color = match &block.properties.color {
+            Some(color) => quote! { #color },
+            None => quote! { None },
+        };
+        let is_solid = match &block.properties.is_solid {
+            Some(is_solid) => quote! { #is_solid },
+            None => quote! { true },
+        };
+        let is_fluid = match &block.properties.is_fluid {
+            Some(is_fluid) => quote! { #is_fluid },
+            None => quote! { false },
+        };
+        let light_level = match &block.properties.light_level {
+            Some(light_level) => quote! { #light_level },
+            None => quote! { 0 },
+        };
+        let hardness = match &block.properties.hardness {
+            Some(hardness) => quote! { #hardness },
+            None => quote! { 1.0 },
+        };
+        let render_layer = match &block.properties.render_layer {
+            Some(render_layer) => quote! { RenderLayer::#render_layer },
+            None => quote! { RenderLayer::Opaque },
+        };
+        let orientation_mode = match &block.properties.orientation_mode {
+            Some(orientation_mode) => quote! { OrientationMode::#orientation_mode },
+            None => quote! { OrientationMode::Fixed },
+        };
+
         let new_block = quote! {
                 BlockInfos {
                     id: #block_id,
                     name: #name,
                     is_transparent: #is_transparent,
                     color: #color,
+                    is_solid: #is_solid,
+                    is_fluid: #is_fluid,
+                    light_level: #light_level,
+                    hardness: #hardness,
+                    render_layer: #render_layer,
+                    orientation_mode: #orientation_mode,
                 },
         };
         array_content = quote! {#array_content #new_block};
@@ -88,12 +252,62 @@ fn generate_blocks_array(blocks: &Punctuated<MyBlock, Token![,]>) -> proc_macro2
 
     let nb_blocks = blocks.len();
     let array = quote! {
-        const BLOCKS: [BlockInfos; #nb_blocks] = [ #array_content ];
+        pub const BLOCKS: [BlockInfos; #nb_blocks] = [ #array_content ];
     };
 
     array
 }
 
+// The properties of any block, built in or from the definitions file, read through
+// the BlockRegistry since the definitions file can change the built in blocks too.
+// They are there for every property even if the game doesn't read all of them yet.
+fn generate_accessors() -> proc_macro2::TokenStream {
+    quote! {
+        #[allow(dead_code)]
+        pub fn block_is_transparent(block_registry: &BlockRegistry, block_id: u16) -> bool {
+            block_registry.is_transparent(block_id)
+        }
+
+        #[allow(dead_code)]
+        pub fn block_color(block_registry: &BlockRegistry, block_id: u16) -> Option<Color> {
+            block_registry.get_color(block_id)
+        }
+
+        #[allow(dead_code)]
+        pub fn block_is_solid(block_registry: &BlockRegistry, block_id: u16) -> bool {
+            block_registry.is_solid(block_id)
+        }
+
+        #[allow(dead_code)]
+        pub fn block_is_fluid(block_registry: &BlockRegistry, block_id: u16) -> bool {
+            block_registry.is_fluid(block_id)
+        }
+
+        #[allow(dead_code)]
+        pub fn block_light_level(block_registry: &BlockRegistry, block_id: u16) -> u8 {
+            block_registry.get_light_level(block_id)
+        }
+
+        #[allow(dead_code)]
+        pub fn block_hardness(block_registry: &BlockRegistry, block_id: u16) -> f32 {
+            block_registry.get_hardness(block_id)
+        }
+
+        #[allow(dead_code)]
+        pub fn block_render_layer(block_registry: &BlockRegistry, block_id: u16) -> RenderLayer {
+            block_registry.get_render_layer(block_id)
+        }
+
+        #[allow(dead_code)]
+        pub fn block_orientation_mode(
+            block_registry: &BlockRegistry,
+            block_id: u16,
+        ) -> OrientationMode {
+            block_registry.get_orientation_mode(block_id)
+        }
+    }
+}
+
 fn generate_get_block_id_function(
     blocks: &Punctuated<MyBlock, Token![,]>,
 ) -> proc_macro2::TokenStream {
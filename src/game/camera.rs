@@ -1,16 +1,47 @@
+use crate::game::key_bindings::{Action, KeyBindings, SETTINGS_PATH};
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk::Chunk;
+use crate::game::world::collision::{move_and_collide, Aabb};
+use crate::game::world::loaded_chunks::get_loaded_block;
+use crate::game::world::raycast::TargetedBlock;
+use crate::game::world::world::World;
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
 use std::f32::consts::PI;
+use std::path::Path;
 
 const MOBILITY_SPEED: f32 = 50.;
 const CAMERA_SENSIBILITY: f32 = 0.005;
 
+const WALKING_SPEED: f32 = 5.;
+const GRAVITY: f32 = 28.;
+const JUMP_SPEED: f32 = 8.5;
+const MAX_FALLING_SPEED: f32 = 60.;
+// the camera is at the eyes of the player, its box goes from its feet to its head
+const PLAYER_WIDTH: f32 = 0.6;
+const PLAYER_HEIGHT: f32 = 1.8;
+const PLAYER_EYES_HEIGHT: f32 = 1.62;
+
 #[derive(Component, Default)]
 pub struct FirstPersonCamera {
     pub has_focus: bool,
 }
 
+// flying go through the blocks, walking collide with them and fall
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MovementMode {
+    #[default]
+    Flying,
+    Walking,
+}
+
+#[derive(Component, Default)]
+pub struct WalkingState {
+    pub vertical_velocity: f32,
+    pub is_on_ground: bool,
+}
+
 #[derive(Component, Default)]
 pub struct Velocity {
     pub x: f32,
@@ -32,6 +63,18 @@ pub struct Rotation {
     pub roll: f32,
 }
 
+impl Rotation {
+    pub fn to_quat(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.)
+    }
+}
+
+// the origin and the direction the camera is looking at, in world space
+pub fn get_camera_ray(position: &Position, rotation: &Rotation) -> (Vec3, Vec3) {
+    let origin = Vec3::new(position.x, position.y, position.z);
+    (origin, rotation.to_quat() * Vec3::NEG_Z)
+}
+
 fn spawn_first_person_camera_system(mut commands: Commands) {
     commands
         .spawn(Camera3dBundle { ..default() })
@@ -48,42 +91,50 @@ fn spawn_first_person_camera_system(mut commands: Commands) {
             x: 3.0,
             y: 1.5,
             z: 0.0,
-        });
+        })
+        .insert(TargetedBlock::default())
+        .insert(MovementMode::default())
+        .insert(WalkingState::default());
 }
 
 fn update_camera_velocity_system(
     keys: Res<Input<KeyCode>>,
-    mut query: Query<(&FirstPersonCamera, &mut Velocity, &Rotation)>,
+    key_bindings: Res<KeyBindings>,
+    mut query: Query<(&FirstPersonCamera, &MovementMode, &mut Velocity, &Rotation)>,
 ) {
-    for (camera, mut velocity, camera_rotation) in query.iter_mut() {
+    for (camera, movement_mode, mut velocity, camera_rotation) in query.iter_mut() {
         if camera.has_focus {
+            let speed = match movement_mode {
+                MovementMode::Flying => MOBILITY_SPEED,
+                MovementMode::Walking => WALKING_SPEED,
+            };
             *velocity = Velocity {
                 x: 0.,
                 y: 0.,
                 z: 0.,
             };
 
-            if keys.pressed(KeyCode::D) {
-                velocity.x += MOBILITY_SPEED * (camera_rotation.yaw + PI / 2.).sin();
-                velocity.z += MOBILITY_SPEED * (camera_rotation.yaw + PI / 2.).cos();
+            if key_bindings.pressed(&keys, Action::Right) {
+                velocity.x += speed * (camera_rotation.yaw + PI / 2.).sin();
+                velocity.z += speed * (camera_rotation.yaw + PI / 2.).cos();
             }
-            if keys.pressed(KeyCode::Q) {
-                velocity.x -= MOBILITY_SPEED * (camera_rotation.yaw + PI / 2.).sin();
-                velocity.z -= MOBILITY_SPEED * (camera_rotation.yaw + PI / 2.).cos();
+            if key_bindings.pressed(&keys, Action::Left) {
+                velocity.x -= speed * (camera_rotation.yaw + PI / 2.).sin();
+                velocity.z -= speed * (camera_rotation.yaw + PI / 2.).cos();
             }
-            if keys.pressed(KeyCode::Space) {
-                velocity.y += MOBILITY_SPEED;
+            if key_bindings.pressed(&keys, Action::Up) {
+                velocity.y += speed;
             }
-            if keys.pressed(KeyCode::LShift) {
-                velocity.y -= MOBILITY_SPEED;
+            if key_bindings.pressed(&keys, Action::Down) {
+                velocity.y -= speed;
             }
-            if keys.pressed(KeyCode::Z) {
-                velocity.x -= MOBILITY_SPEED * camera_rotation.yaw.sin();
-                velocity.z -= MOBILITY_SPEED * camera_rotation.yaw.cos();
+            if key_bindings.pressed(&keys, Action::Forward) {
+                velocity.x -= speed * camera_rotation.yaw.sin();
+                velocity.z -= speed * camera_rotation.yaw.cos();
             }
-            if keys.pressed(KeyCode::S) {
-                velocity.x += MOBILITY_SPEED * camera_rotation.yaw.sin();
-                velocity.z += MOBILITY_SPEED * camera_rotation.yaw.cos();
+            if key_bindings.pressed(&keys, Action::Back) {
+                velocity.x += speed * camera_rotation.yaw.sin();
+                velocity.z += speed * camera_rotation.yaw.cos();
             }
         }
     }
@@ -91,10 +142,10 @@ fn update_camera_velocity_system(
 
 fn update_camera_position_system(
     time: Res<Time>,
-    mut query: Query<(&FirstPersonCamera, &Velocity, &mut Position)>,
+    mut query: Query<(&FirstPersonCamera, &MovementMode, &Velocity, &mut Position)>,
 ) {
-    for (camera, velocity, mut position) in query.iter_mut() {
-        if camera.has_focus {
+    for (camera, movement_mode, velocity, mut position) in query.iter_mut() {
+        if camera.has_focus && *movement_mode == MovementMode::Flying {
             position.x += velocity.x * time.delta_seconds();
             position.y += velocity.y * time.delta_seconds();
             position.z += velocity.z * time.delta_seconds();
@@ -102,6 +153,79 @@ fn update_camera_position_system(
     }
 }
 
+// the vertical velocity of the camera is ignored, the player fall and jump instead
+fn update_walking_camera_position_system(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    block_registry: Res<BlockRegistry>,
+    worlds: Query<&World>,
+    chunks: Query<&Chunk>,
+    mut query: Query<(
+        &FirstPersonCamera,
+        &MovementMode,
+        &Velocity,
+        &mut WalkingState,
+        &mut Position,
+    )>,
+) {
+    let Ok(world) = worlds.get_single() else {
+        return;
+    };
+    // the blocks of the chunks not loaded yet are solid so the player can't fall through them
+    let is_solid = |block_position: IVec3| {
+        get_loaded_block(world, |entity| chunks.get(entity).ok(), block_position)
+            .is_none_or(|block| block_registry.is_solid(block.get_id()))
+    };
+
+    for (camera, movement_mode, velocity, mut walking_state, mut position) in query.iter_mut() {
+        if !camera.has_focus || *movement_mode != MovementMode::Walking {
+            continue;
+        }
+
+        if walking_state.is_on_ground && key_bindings.pressed(&keys, Action::Up) {
+            walking_state.vertical_velocity = JUMP_SPEED;
+        }
+        walking_state.vertical_velocity -= GRAVITY * time.delta_seconds();
+        walking_state.vertical_velocity = walking_state.vertical_velocity.max(-MAX_FALLING_SPEED);
+
+        let motion = Vec3::new(velocity.x, walking_state.vertical_velocity, velocity.z)
+            * time.delta_seconds();
+        let feet_position = Vec3::new(position.x, position.y - PLAYER_EYES_HEIGHT, position.z);
+        let aabb = Aabb::from_feet_position(feet_position, PLAYER_WIDTH, PLAYER_HEIGHT);
+        let collision = move_and_collide(&aabb, motion, walking_state.is_on_ground, is_solid);
+
+        walking_state.is_on_ground = collision.is_on_ground(motion);
+        if collision.collided.y {
+            walking_state.vertical_velocity = 0.;
+        }
+        position.x += collision.motion.x;
+        position.y += collision.motion.y;
+        position.z += collision.motion.z;
+    }
+}
+
+fn toggle_movement_mode_system(
+    keys: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut query: Query<(&FirstPersonCamera, &mut MovementMode, &mut WalkingState)>,
+) {
+    if !key_bindings.just_pressed(&keys, Action::ToggleMovementMode) {
+        return;
+    }
+
+    for (camera, mut movement_mode, mut walking_state) in query.iter_mut() {
+        if !camera.has_focus {
+            continue;
+        }
+        *movement_mode = match *movement_mode {
+            MovementMode::Flying => MovementMode::Walking,
+            MovementMode::Walking => MovementMode::Flying,
+        };
+        *walking_state = WalkingState::default();
+    }
+}
+
 fn move_camera_system(mut query: Query<(&Position, &mut Transform), With<FirstPersonCamera>>) {
     for (position, mut transform) in query.iter_mut() {
         let translation = &mut transform.translation;
@@ -140,27 +264,27 @@ fn update_camera_rotation_system(
 
 fn rotate_camera_system(mut query: Query<(&Rotation, &mut Transform), With<FirstPersonCamera>>) {
     for (rotation, mut transform) in query.iter_mut() {
-        transform.rotation = Quat::from_euler(EulerRot::YXZ, rotation.yaw, rotation.pitch, 0.);
+        transform.rotation = rotation.to_quat();
     }
 }
 
-fn cursor_grab_system(
+pub fn cursor_grab_system(
     mut primary_query: Query<&mut Window, With<PrimaryWindow>>,
     btn: Res<Input<MouseButton>>,
     key: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
     mut query: Query<&mut FirstPersonCamera>,
 ) {
     let Ok(mut primary) = primary_query.get_single_mut() else {
         return;
     };
     for mut camera in query.iter_mut() {
-        if btn.just_pressed(MouseButton::Right) {
+        let toggle_focus = key_bindings.just_pressed(&key, Action::ToggleFocus);
+        if btn.just_pressed(MouseButton::Right) || (toggle_focus && !camera.has_focus) {
             primary.cursor.grab_mode = CursorGrabMode::Locked;
             primary.cursor.visible = false;
             camera.has_focus = true;
-        }
-
-        if key.just_pressed(KeyCode::Escape) {
+        } else if toggle_focus {
             primary.cursor.grab_mode = CursorGrabMode::None;
             primary.cursor.visible = true;
             camera.has_focus = false;
@@ -170,10 +294,13 @@ fn cursor_grab_system(
 
 impl Plugin for FirstPersonCamera {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_first_person_camera_system)
+        app.insert_resource(KeyBindings::load(Path::new(SETTINGS_PATH)))
+            .add_startup_system(spawn_first_person_camera_system)
             .add_system(cursor_grab_system)
             .add_system(update_camera_velocity_system)
             .add_system(update_camera_position_system)
+            .add_system(update_walking_camera_position_system)
+            .add_system(toggle_movement_mode_system)
             .add_system(move_camera_system)
             .add_system(update_camera_rotation_system)
             .add_system(rotate_camera_system);
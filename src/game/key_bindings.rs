@@ -0,0 +1,154 @@
+use bevy::log::error;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+pub const SETTINGS_PATH: &str = "settings.toml";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Forward,
+    Back,
+    Left,
+    Right,
+    // jump when walking
+    Up,
+    Down,
+    ToggleFocus,
+    ToggleMovementMode,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyboardPreset {
+    Qwerty,
+    #[default]
+    Azerty,
+}
+
+impl KeyboardPreset {
+    fn get_keys(&self) -> HashMap<Action, KeyCode> {
+        let (forward, left) = match self {
+            Self::Qwerty => (KeyCode::W, KeyCode::A),
+            Self::Azerty => (KeyCode::Z, KeyCode::Q),
+        };
+
+        HashMap::from([
+            (Action::Forward, forward),
+            (Action::Back, KeyCode::S),
+            (Action::Left, left),
+            (Action::Right, KeyCode::D),
+            (Action::Up, KeyCode::Space),
+            (Action::Down, KeyCode::LShift),
+            (Action::ToggleFocus, KeyCode::Escape),
+            (Action::ToggleMovementMode, KeyCode::F),
+        ])
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct SettingsFile {
+    #[serde(default)]
+    key_bindings: KeyBindingsSettings,
+}
+
+// the keys set in the file replace the ones of the preset
+#[derive(Deserialize, Default)]
+struct KeyBindingsSettings {
+    #[serde(default)]
+    preset: KeyboardPreset,
+    #[serde(default)]
+    keys: KeysSettings,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct KeysSettings {
+    forward: Option<KeyCode>,
+    back: Option<KeyCode>,
+    left: Option<KeyCode>,
+    right: Option<KeyCode>,
+    up: Option<KeyCode>,
+    down: Option<KeyCode>,
+    toggle_focus: Option<KeyCode>,
+    toggle_movement_mode: Option<KeyCode>,
+}
+
+impl KeysSettings {
+    fn get_keys(&self) -> HashMap<Action, KeyCode> {
+        let keys = [
+            (Action::Forward, self.forward),
+            (Action::Back, self.back),
+            (Action::Left, self.left),
+            (Action::Right, self.right),
+            (Action::Up, self.up),
+            (Action::Down, self.down),
+            (Action::ToggleFocus, self.toggle_focus),
+            (Action::ToggleMovementMode, self.toggle_movement_mode),
+        ];
+        keys.into_iter()
+            .filter_map(|(action, key)| Some((action, key?)))
+            .collect()
+    }
+}
+
+// The key of each action, read from the settings file when the game start
+#[derive(Resource)]
+pub struct KeyBindings {
+    keys: HashMap<Action, KeyCode>,
+}
+
+impl KeyBindings {
+    pub fn from_preset(preset: KeyboardPreset) -> Self {
+        Self {
+            keys: preset.get_keys(),
+        }
+    }
+
+    // an error in the file is logged and the default preset is used
+    pub fn load(settings_path: &Path) -> Self {
+        let settings = match load_settings(settings_path) {
+            Ok(settings) => settings,
+            Err(err) => {
+                error!("Unable to read the settings {:?} : {}", settings_path, err);
+                SettingsFile::default()
+            }
+        };
+
+        let mut key_bindings = Self::from_preset(settings.key_bindings.preset);
+        key_bindings
+            .keys
+            .extend(settings.key_bindings.keys.get_keys());
+        key_bindings
+    }
+
+    pub fn get_key(&self, action: Action) -> KeyCode {
+        self.keys[&action]
+    }
+
+    pub fn pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        keys.pressed(self.get_key(action))
+    }
+
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        keys.just_pressed(self.get_key(action))
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::from_preset(KeyboardPreset::default())
+    }
+}
+
+fn load_settings(path: &Path) -> Result<SettingsFile> {
+    if !path.exists() {
+        return Ok(SettingsFile::default());
+    }
+
+    let content = fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))
+}
@@ -1,3 +1,5 @@
 // the camera used to move around in the scene
 pub mod camera;
+// the keys of the actions, read from the settings file
+pub mod key_bindings;
 pub mod world;
@@ -1,14 +1,22 @@
-// The Block struct represent a block by a u16 like this -> IIII IIII IIIU UOOO
+// The Block struct represent a block by a u16 like this -> IIII IIII IIIS SOOO
 // 11 bits for the block ID represented here with the letter "I"
 // 3 bits for the block orientation represented with the letter "O"
-// 2 bits unused marked as "U"
+// 2 bits for the state of the block marked as "S"
+// The fluids don't have an orientation, the "O" bits hold their level instead.
+// The state is a small value whose meaning depends on the block (like a growth
+// stage), the data that doesn't fit in it goes in a BlockEntity.
+
+use bevy::prelude::IVec3;
 
 const AIR_BLOCK: u16 = 0x0000;
+// a fluid at level 0 fill its whole block, it gets lower with each level
+pub const MAX_FLUID_LEVEL: u8 = 7;
+pub const MAX_BLOCK_STATE: u8 = 3;
 
 #[derive(Clone, Copy, Default, PartialEq, Eq)]
 pub struct Block(pub u16);
 
-#[derive(Default)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
 pub enum BlockOrientation {
     #[default]
     PositiveX,
@@ -19,6 +27,32 @@ pub enum BlockOrientation {
     NegativeZ,
 }
 
+impl BlockOrientation {
+    // None when the direction isn't along one of the axis
+    pub fn from_direction(direction: IVec3) -> Option<Self> {
+        match direction.to_array() {
+            [1, 0, 0] => Some(Self::PositiveX),
+            [-1, 0, 0] => Some(Self::NegativeX),
+            [0, 1, 0] => Some(Self::PositiveY),
+            [0, -1, 0] => Some(Self::NegativeY),
+            [0, 0, 1] => Some(Self::PositiveZ),
+            [0, 0, -1] => Some(Self::NegativeZ),
+            _ => None,
+        }
+    }
+
+    pub fn get_opposite(&self) -> Self {
+        match self {
+            Self::PositiveX => Self::NegativeX,
+            Self::NegativeX => Self::PositiveX,
+            Self::PositiveY => Self::NegativeY,
+            Self::NegativeY => Self::PositiveY,
+            Self::PositiveZ => Self::NegativeZ,
+            Self::NegativeZ => Self::PositiveZ,
+        }
+    }
+}
+
 impl Block {
     pub fn new(block_id: u16, orientation: BlockOrientation) -> Self {
         let mut block = Self::default();
@@ -31,17 +65,18 @@ impl Block {
         Block(AIR_BLOCK)
     }
 
+    // the codes 6 and 7 are only used by the levels of the fluids, they
+    // give the default orientation
     pub fn get_orientation(&self) -> BlockOrientation {
         let orientation_code = (self.0 << 13) >> 13;
 
         match orientation_code {
-            0 => BlockOrientation::PositiveX,
             1 => BlockOrientation::NegativeX,
             2 => BlockOrientation::PositiveY,
             3 => BlockOrientation::NegativeY,
             4 => BlockOrientation::PositiveZ,
             5 => BlockOrientation::NegativeZ,
-            _ => unreachable!(),
+            _ => BlockOrientation::default(),
         }
     }
 
@@ -69,6 +104,28 @@ impl Block {
         }
     }
 
+    #[inline]
+    pub fn get_fluid_level(&self) -> u8 {
+        (self.0 & 0x7) as u8
+    }
+
+    #[inline]
+    pub fn set_fluid_level(&mut self, level: u8) {
+        self.0 &= 0xFFF8;
+        self.0 |= level.min(MAX_FLUID_LEVEL) as u16;
+    }
+
+    #[inline]
+    pub fn get_state(&self) -> u8 {
+        ((self.0 >> 3) & 0x3) as u8
+    }
+
+    #[inline]
+    pub fn set_state(&mut self, state: u8) {
+        self.0 &= 0xFFE7;
+        self.0 |= (state.min(MAX_BLOCK_STATE) as u16) << 3;
+    }
+
     pub fn set_id(&mut self, block_id: u16) {
         self.0 &= 0x001F;
         let id_shifted = block_id << 5;
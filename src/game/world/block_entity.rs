@@ -0,0 +1,119 @@
+use super::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+use std::collections::BTreeMap;
+
+// the limits of the saved block entities, their lengths are stored on 2 or 4 bytes (see chunk_serializer)
+pub const MAX_BLOCK_ENTITY_VALUES: usize = u16::MAX as usize;
+pub const MAX_VALUE_NAME_LEN: usize = u16::MAX as usize;
+pub const MAX_VALUE_LEN: usize = u32::MAX as usize;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum BlockEntityValue {
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl BlockEntityValue {
+    // the number of bytes of a text or of bytes, 0 for the numbers
+    fn get_len(&self) -> usize {
+        match self {
+            BlockEntityValue::Integer(_) | BlockEntityValue::Float(_) => 0,
+            BlockEntityValue::Text(text) => text.len(),
+            BlockEntityValue::Bytes(bytes) => bytes.len(),
+        }
+    }
+}
+
+// a value refused by a BlockEntity because it couldn't be saved
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockEntityError {
+    TooManyValues,
+    NameTooLong,
+    ValueTooLong,
+}
+
+// The data of a block that doesn't fit in the state bits of the Block, like the
+// content of a container. Each value is stored under a name chosen by the block.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct BlockEntity {
+    values: BTreeMap<String, BlockEntityValue>,
+}
+
+impl BlockEntity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&BlockEntityValue> {
+        self.values.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, value: BlockEntityValue) -> Result<(), BlockEntityError> {
+        if !self.values.contains_key(name) && self.values.len() >= MAX_BLOCK_ENTITY_VALUES {
+            return Err(BlockEntityError::TooManyValues);
+        }
+        if name.len() > MAX_VALUE_NAME_LEN {
+            return Err(BlockEntityError::NameTooLong);
+        }
+        if value.get_len() > MAX_VALUE_LEN {
+            return Err(BlockEntityError::ValueTooLong);
+        }
+
+        self.values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<BlockEntityValue> {
+        self.values.remove(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &BlockEntityValue)> {
+        self.values.iter()
+    }
+}
+
+// The block entities of a chunk, only the few blocks having one are stored.
+// They are saved with the blocks of the chunk.
+#[derive(Clone, Default)]
+pub struct ChunkBlockEntities {
+    block_entities: BTreeMap<BlockCoordsInChunk, BlockEntity>,
+}
+
+impl ChunkBlockEntities {
+    pub fn get(&self, block_coords: &BlockCoordsInChunk) -> Option<&BlockEntity> {
+        self.block_entities.get(block_coords)
+    }
+
+    pub fn get_mut(&mut self, block_coords: &BlockCoordsInChunk) -> Option<&mut BlockEntity> {
+        self.block_entities.get_mut(block_coords)
+    }
+
+    pub fn set(&mut self, block_coords: BlockCoordsInChunk, block_entity: BlockEntity) {
+        self.block_entities.insert(block_coords, block_entity);
+    }
+
+    pub fn remove(&mut self, block_coords: &BlockCoordsInChunk) -> Option<BlockEntity> {
+        self.block_entities.remove(block_coords)
+    }
+
+    pub fn len(&self) -> usize {
+        self.block_entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.block_entities.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&BlockCoordsInChunk, &BlockEntity)> {
+        self.block_entities.iter()
+    }
+}
@@ -0,0 +1,383 @@
+use super::block::{Block, BlockOrientation};
+use super::chunk_renderer::blocks_resources::{BlockInfos, OrientationMode, RenderLayer, BLOCKS};
+use super::chunk_renderer::renderer::Face;
+use super::light::chunk_light::MAX_LIGHT_LEVEL;
+use bevy::log::error;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+pub const BLOCKS_DEFINITIONS_PATH: &str = "assets/blocks.toml";
+pub const BLOCKS_IDS_FILE_NAME: &str = "blocks_ids.toml";
+
+// the block id only has 11 bits in a Block
+const MAX_NB_BLOCKS: usize = 1 << 11;
+// The built in blocks use the ids below this one and the blocks only in the
+// definitions file the ones above, so a new built in block never take the id
+// a world has already given to one of them.
+const FIRST_DATA_DEFINED_ID: u16 = 1024;
+
+#[derive(Deserialize, Clone)]
+pub struct BlockDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub is_transparent: bool,
+    // hue in degrees, saturation, lightness and alpha
+    #[serde(default)]
+    pub color: Option<[f32; 4]>,
+    #[serde(default = "default_is_solid")]
+    pub is_solid: bool,
+    #[serde(default)]
+    pub is_fluid: bool,
+    #[serde(default)]
+    pub light_level: u8,
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+    #[serde(default)]
+    pub render_layer: RenderLayer,
+    #[serde(default)]
+    pub orientation_mode: OrientationMode,
+    #[serde(default)]
+    pub textures: BlockTextures,
+    #[serde(default)]
+    pub faces_colors: BlockFacesColors,
+}
+
+// the name of the texture of each face, without its extension, a face without
+// texture use the color of the face or of the block (see BlockTextureAtlas)
+#[derive(Deserialize, Clone, Default)]
+pub struct BlockTextures {
+    pub top: Option<String>,
+    pub side: Option<String>,
+    pub bottom: Option<String>,
+    // only seen on the Facing blocks, the side is used without it
+    pub front: Option<String>,
+}
+
+// the color of each face, in the same format as the color of the block
+#[derive(Deserialize, Clone, Default)]
+pub struct BlockFacesColors {
+    pub top: Option<[f32; 4]>,
+    pub side: Option<[f32; 4]>,
+    pub bottom: Option<[f32; 4]>,
+    pub front: Option<[f32; 4]>,
+}
+
+// the faces of a block before it is oriented, the top of a block that isn't
+// oriented is always toward positive Y
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockFace {
+    Top,
+    Side,
+    Bottom,
+    Front,
+}
+
+fn default_is_solid() -> bool {
+    true
+}
+
+fn default_hardness() -> f32 {
+    1.0
+}
+
+impl BlockDefinition {
+    fn from_block_infos(block_infos: &BlockInfos) -> Self {
+        Self {
+            name: block_infos.name.to_string(),
+            is_transparent: block_infos.is_transparent,
+            color: block_infos.color.map(|color| color.as_hsla_f32()),
+            is_solid: block_infos.is_solid,
+            is_fluid: block_infos.is_fluid,
+            light_level: block_infos.light_level,
+            hardness: block_infos.hardness,
+            render_layer: block_infos.render_layer,
+            orientation_mode: block_infos.orientation_mode,
+            textures: BlockTextures::default(),
+            faces_colors: BlockFacesColors::default(),
+        }
+    }
+
+    pub fn get_color(&self) -> Option<Color> {
+        self.color.map(get_hsla_color)
+    }
+
+    // the color of the face, or the color of the block when the face doesn't have one
+    pub fn get_face_color(&self, block_face: BlockFace) -> Option<Color> {
+        let face_color = match block_face {
+            BlockFace::Top => self.faces_colors.top,
+            BlockFace::Side => self.faces_colors.side,
+            BlockFace::Bottom => self.faces_colors.bottom,
+            BlockFace::Front => self.faces_colors.front.or(self.faces_colors.side),
+        };
+        face_color.map(get_hsla_color).or_else(|| self.get_color())
+    }
+}
+
+fn get_hsla_color([hue, saturation, lightness, alpha]: [f32; 4]) -> Color {
+    Color::hsla(hue, saturation, lightness, alpha)
+}
+
+#[derive(Deserialize)]
+struct BlocksDefinitionsFile {
+    blocks: Vec<BlockDefinition>,
+}
+
+// All the blocks the game know about, indexed by their id.
+// The blocks created with create_blocks! are always registered with the id of
+// their constant in blocks_ids, the definitions file can change their properties
+// and add new blocks. The id given to each name is saved with the world so
+// reordering or removing blocks from the file never change a saved block, and a
+// block whose saved id is now used by another block is refused, not renumbered.
+#[derive(Resource, Clone)]
+pub struct BlockRegistry {
+    blocks: Vec<Option<BlockDefinition>>,
+    ids_by_name: HashMap<String, u16>,
+}
+
+impl BlockRegistry {
+    // only the built in blocks
+    pub fn new() -> Self {
+        let mut registry = Self {
+            blocks: Vec::new(),
+            ids_by_name: HashMap::new(),
+        };
+        for block_infos in BLOCKS.iter() {
+            assert!(block_infos.id < FIRST_DATA_DEFINED_ID);
+            registry.register(
+                block_infos.id,
+                BlockDefinition::from_block_infos(block_infos),
+            );
+        }
+        registry
+    }
+
+    // an error in one of the files is logged and the blocks it contain are ignored,
+    // the built in blocks are always there
+    pub fn load(definitions_path: &Path, world_directory: &Path) -> Self {
+        let mut registry = Self::new();
+        let ids_path = world_directory.join(BLOCKS_IDS_FILE_NAME);
+
+        let mut saved_ids = match load_blocks_ids(&ids_path) {
+            Ok(saved_ids) => saved_ids,
+            Err(err) => {
+                error!("Unable to read the blocks ids {:?} : {}", ids_path, err);
+                BTreeMap::new()
+            }
+        };
+
+        let definitions = match load_blocks_definitions(definitions_path) {
+            Ok(definitions) => definitions,
+            Err(err) => {
+                error!(
+                    "Unable to read the blocks definitions {:?} : {}",
+                    definitions_path, err
+                );
+                Vec::new()
+            }
+        };
+
+        for definition in definitions {
+            let id = match registry.get_block_id(&definition.name) {
+                Some(id) => id,
+                None => match get_saved_id(&saved_ids, &definition.name) {
+                    Some(id) => {
+                        // the blocks saved with this id can't follow the block to another id
+                        if let Some(other_definition) = registry.get_definition(id) {
+                            error!(
+                                "Unable to register the block {} : its saved id {} is used by {}",
+                                definition.name, id, other_definition.name
+                            );
+                            continue;
+                        }
+                        id
+                    }
+                    None => match get_free_id(&registry, &saved_ids) {
+                        Some(id) => id,
+                        None => {
+                            error!("No block id left for the block {}", definition.name);
+                            continue;
+                        }
+                    },
+                },
+            };
+
+            registry.register(id, definition);
+        }
+
+        // the ids of the blocks missing from the file are kept so they are not given to another block
+        for (name, id) in registry.ids_by_name.iter() {
+            saved_ids.insert(name.clone(), *id);
+        }
+        if let Err(err) = save_blocks_ids(world_directory, &ids_path, &saved_ids) {
+            error!("Unable to save the blocks ids {:?} : {}", ids_path, err);
+        }
+
+        registry
+    }
+
+    fn register(&mut self, id: u16, definition: BlockDefinition) {
+        let index = id as usize;
+        if index >= self.blocks.len() {
+            self.blocks.resize(index + 1, None);
+        }
+        self.ids_by_name.insert(definition.name.clone(), id);
+        self.blocks[index] = Some(definition);
+    }
+
+    fn is_registered(&self, id: u16) -> bool {
+        self.get_definition(id).is_some()
+    }
+
+    pub fn get_block_id(&self, name: &str) -> Option<u16> {
+        self.ids_by_name.get(name).copied()
+    }
+
+    // every id below this one may be a registered block
+    pub fn get_nb_ids(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn get_definition(&self, id: u16) -> Option<&BlockDefinition> {
+        self.blocks.get(id as usize)?.as_ref()
+    }
+
+    // an unknown block (removed from the definitions file) is considered opaque
+    pub fn is_transparent(&self, id: u16) -> bool {
+        self.get_definition(id)
+            .is_some_and(|definition| definition.is_transparent)
+    }
+
+    // an unknown block is considered solid
+    pub fn is_solid(&self, id: u16) -> bool {
+        self.get_definition(id)
+            .is_none_or(|definition| definition.is_solid)
+    }
+
+    pub fn is_fluid(&self, id: u16) -> bool {
+        self.get_definition(id)
+            .is_some_and(|definition| definition.is_fluid)
+    }
+
+    // the unknown blocks have no color
+    pub fn get_color(&self, id: u16) -> Option<Color> {
+        self.get_definition(id)?.get_color()
+    }
+
+    // an unknown block has the default hardness
+    pub fn get_hardness(&self, id: u16) -> f32 {
+        self.get_definition(id)
+            .map_or(default_hardness(), |definition| definition.hardness)
+    }
+
+    // the unknown blocks are opaque
+    pub fn get_render_layer(&self, id: u16) -> RenderLayer {
+        self.get_definition(id)
+            .map_or(RenderLayer::Opaque, |definition| definition.render_layer)
+    }
+
+    // the orientation bits of the fluids hold their level, they are never oriented
+    pub fn get_orientation_mode(&self, id: u16) -> OrientationMode {
+        self.get_definition(id)
+            .filter(|definition| !definition.is_fluid)
+            .map_or(OrientationMode::Fixed, |definition| {
+                definition.orientation_mode
+            })
+    }
+
+    // The face of the block seen from this side of the world once it is oriented.
+    // The top of an Axis block is toward its orientation, the front of a Facing block
+    // looks toward its orientation.
+    pub fn get_block_face(&self, block: Block, face: Face) -> BlockFace {
+        let face_orientation = match face {
+            Face::PositiveX => BlockOrientation::PositiveX,
+            Face::NegativeX => BlockOrientation::NegativeX,
+            Face::PositiveY => BlockOrientation::PositiveY,
+            Face::NegativeY => BlockOrientation::NegativeY,
+            Face::PositiveZ => BlockOrientation::PositiveZ,
+            Face::NegativeZ => BlockOrientation::NegativeZ,
+        };
+        let (top, front) = match self.get_orientation_mode(block.get_id()) {
+            OrientationMode::Fixed => (BlockOrientation::PositiveY, None),
+            OrientationMode::Axis => (block.get_orientation(), None),
+            OrientationMode::Facing => (BlockOrientation::PositiveY, Some(block.get_orientation())),
+        };
+
+        if front == Some(face_orientation) {
+            BlockFace::Front
+        } else if face_orientation == top {
+            BlockFace::Top
+        } else if face_orientation == top.get_opposite() {
+            BlockFace::Bottom
+        } else {
+            BlockFace::Side
+        }
+    }
+
+    // the level of the light emitted by the block, 0 when it doesn't emit any
+    pub fn get_light_level(&self, id: u16) -> u8 {
+        self.get_definition(id)
+            .map_or(0, |definition| definition.light_level.min(MAX_LIGHT_LEVEL))
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// An id out of the range of the data defined blocks is refused, it could be
+// truncated in a Block or given to a built in block later. The block gets a new id.
+fn get_saved_id(saved_ids: &BTreeMap<String, u16>, name: &str) -> Option<u16> {
+    let id = *saved_ids.get(name)?;
+    if !(FIRST_DATA_DEFINED_ID..MAX_NB_BLOCKS as u16).contains(&id) {
+        error!(
+            "Unable to use the saved id {} of the block {} : it is not between {} and {}",
+            id,
+            name,
+            FIRST_DATA_DEFINED_ID,
+            MAX_NB_BLOCKS - 1
+        );
+        return None;
+    }
+    Some(id)
+}
+
+// the free ids are only taken from the ones of the blocks of the definitions file
+fn get_free_id(registry: &BlockRegistry, saved_ids: &BTreeMap<String, u16>) -> Option<u16> {
+    (FIRST_DATA_DEFINED_ID..MAX_NB_BLOCKS as u16).find(|id| {
+        !registry.is_registered(*id) && !saved_ids.values().any(|saved_id| saved_id == id)
+    })
+}
+
+fn load_blocks_definitions(path: &Path) -> Result<Vec<BlockDefinition>> {
+    let content = fs::read_to_string(path)?;
+    let file: BlocksDefinitionsFile = toml::from_str(&content)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+    Ok(file.blocks)
+}
+
+// the ids are stored as "name = id" lines
+fn load_blocks_ids(path: &Path) -> Result<BTreeMap<String, u16>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let content = fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))
+}
+
+fn save_blocks_ids(
+    world_directory: &Path,
+    path: &Path,
+    blocks_ids: &BTreeMap<String, u16>,
+) -> Result<()> {
+    let content = toml::to_string(blocks_ids)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+    fs::create_dir_all(world_directory)?;
+    fs::write(path, content)
+}
@@ -1,38 +1,71 @@
 use super::block::Block;
+use super::block_entity::{BlockEntity, ChunkBlockEntities};
+use super::chunk_section::ChunkSection;
 
+use super::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
 use super::coordinates::chunk_coordinates::ChunkCoordinates;
-use super::world_generator::generator::WorldGenNoises;
+use super::light::chunk_light::ChunkLight;
 use bevy::prelude::*;
 
 pub const CHUNK_SIZE: usize = 16;
 pub const CHUNK_HEIGHT: usize = 256;
-pub const NB_BLOCKS_PER_CHUNK: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_HEIGHT;
-
-pub trait AbleToGenerateChunk {
-    fn generate_chunk(&self, chunk: &mut Chunk, noises: WorldGenNoises);
-}
+pub const SECTION_HEIGHT: usize = 16;
+pub const NB_SECTIONS_PER_CHUNK: usize = CHUNK_HEIGHT / SECTION_HEIGHT;
 
+// The chunk is cut vertically into sections, each one is either a single
+// block or palette compressed so most of the chunk cost almost nothing
 #[derive(Clone)]
-pub struct ChunkData(Vec<Block>);
+pub struct ChunkData {
+    sections: Vec<ChunkSection>,
+}
 
 impl Default for ChunkData {
     fn default() -> Self {
-        let chunk_data = vec![Block::default(); NB_BLOCKS_PER_CHUNK];
-        Self(chunk_data)
+        let sections = vec![ChunkSection::default(); NB_SECTIONS_PER_CHUNK];
+        Self { sections }
     }
 }
 
+// convert an index in the chunk into the index of its section and its index in that section
+pub fn get_section_indices(index: usize) -> (usize, usize) {
+    let x = index / (CHUNK_SIZE * CHUNK_HEIGHT);
+    let y = (index / CHUNK_SIZE) % CHUNK_HEIGHT;
+    let z = index % CHUNK_SIZE;
+
+    let section_index = y / SECTION_HEIGHT;
+    let y_in_section = y % SECTION_HEIGHT;
+    let index_in_section = x * (CHUNK_SIZE * SECTION_HEIGHT) + y_in_section * CHUNK_SIZE + z;
+    (section_index, index_in_section)
+}
+
 impl ChunkData {
+    pub fn from_sections(sections: Vec<ChunkSection>) -> Self {
+        assert!(sections.len() == NB_SECTIONS_PER_CHUNK);
+        Self { sections }
+    }
+
     pub fn get_block(&self, index: usize) -> Block {
-        self.0[index]
+        let (section_index, index_in_section) = get_section_indices(index);
+        self.sections[section_index].get_block(index_in_section)
     }
 
-    pub fn as_slice(&self) -> &[Block] {
-        self.0.as_slice()
+    pub fn set_block(&mut self, index: usize, block: Block) {
+        let (section_index, index_in_section) = get_section_indices(index);
+        self.sections[section_index].set_block(index_in_section, block);
     }
 
-    pub fn as_mut_slice(&mut self) -> &mut [Block] {
-        self.0.as_mut_slice()
+    pub fn get_section(&self, section_index: usize) -> &ChunkSection {
+        &self.sections[section_index]
+    }
+
+    pub fn get_sections(&self) -> &[ChunkSection] {
+        self.sections.as_slice()
+    }
+
+    pub fn optimize_sections(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.optimize();
+        }
     }
 }
 
@@ -40,6 +73,11 @@ impl ChunkData {
 pub struct Chunk {
     coords: ChunkCoordinates,
     pub blocks: ChunkData,
+    pub block_entities: ChunkBlockEntities,
+    // computed when the chunk is loaded, it is not saved
+    pub light: ChunkLight,
+    // true when the chunk differ from what is saved on the disk
+    is_dirty: bool,
 }
 
 impl Chunk {
@@ -47,6 +85,9 @@ impl Chunk {
         Chunk {
             coords: ChunkCoordinates::new(x, y, z),
             blocks: ChunkData::default(),
+            block_entities: ChunkBlockEntities::default(),
+            light: ChunkLight::default(),
+            is_dirty: true,
         }
     }
 
@@ -54,15 +95,60 @@ impl Chunk {
         self.coords.clone()
     }
 
-    pub fn as_slice(&self) -> &[Block] {
-        self.blocks.as_slice()
+    pub fn get_block(&self, index: usize) -> Block {
+        self.blocks.get_block(index)
+    }
+
+    // The chunk will have to be saved again. The block entity of the block that
+    // was there is removed when it is replaced by another block.
+    pub fn set_block(&mut self, index: usize, block: Block) {
+        if self.blocks.get_block(index).get_id() != block.get_id() {
+            let block_coords = BlockCoordsInChunk::from_block_index(index);
+            self.block_entities.remove(&block_coords);
+        }
+        self.blocks.set_block(index, block);
+        self.mark_as_dirty();
     }
 
-    pub fn as_mut_slice(&mut self) -> &mut [Block] {
-        self.blocks.as_mut_slice()
+    pub fn get_block_entity(&self, block_coords: &BlockCoordsInChunk) -> Option<&BlockEntity> {
+        self.block_entities.get(block_coords)
     }
 
-    pub fn get_block(&self, index: usize) -> Block {
-        self.blocks.get_block(index)
+    // the chunk will have to be saved again
+    pub fn get_block_entity_mut(
+        &mut self,
+        block_coords: &BlockCoordsInChunk,
+    ) -> Option<&mut BlockEntity> {
+        self.mark_as_dirty();
+        self.block_entities.get_mut(block_coords)
+    }
+
+    pub fn set_block_entity(
+        &mut self,
+        block_coords: BlockCoordsInChunk,
+        block_entity: BlockEntity,
+    ) {
+        self.block_entities.set(block_coords, block_entity);
+        self.mark_as_dirty();
+    }
+
+    pub fn remove_block_entity(
+        &mut self,
+        block_coords: &BlockCoordsInChunk,
+    ) -> Option<BlockEntity> {
+        self.mark_as_dirty();
+        self.block_entities.remove(block_coords)
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    pub fn mark_as_dirty(&mut self) {
+        self.is_dirty = true;
+    }
+
+    pub fn mark_as_saved(&mut self) {
+        self.is_dirty = false;
     }
 }
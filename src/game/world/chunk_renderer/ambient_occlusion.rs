@@ -0,0 +1,50 @@
+use super::renderer::Face;
+
+// the ambient occlusion of a corner without any block around it
+pub const NO_AMBIENT_OCCLUSION: u8 = 3;
+// the ambient occlusion of the 4 corners of a face, in the order of its vertices
+pub type FaceAmbientOcclusion = [u8; 4];
+
+// The corners of a face in the order of its vertices in pre_mesh_to_bundle_conveter,
+// each one is the direction toward the blocks touching it in front of the face.
+fn get_face_corners(face: Face) -> [(i32, i32, i32); 4] {
+    match face {
+        Face::PositiveX | Face::NegativeX => [(0, 1, 1), (0, -1, 1), (0, -1, -1), (0, 1, -1)],
+        Face::PositiveY | Face::NegativeY => [(1, 0, 1), (-1, 0, 1), (-1, 0, -1), (1, 0, -1)],
+        Face::PositiveZ | Face::NegativeZ => [(1, 1, 0), (1, -1, 0), (-1, -1, 0), (-1, 1, 0)],
+    }
+}
+
+// Each corner of the face is darkened by the blocks on its two sides and on its corner,
+// in the layer of blocks in front of the face. A corner between two blocks is fully
+// occluded whatever the block on the corner is.
+pub fn get_face_ambient_occlusion(
+    face: Face,
+    front_position: (i32, i32, i32),
+    is_occluding: impl Fn(i32, i32, i32) -> bool,
+) -> FaceAmbientOcclusion {
+    let (x, y, z) = front_position;
+    get_face_corners(face).map(|(corner_x, corner_y, corner_z)| {
+        // the corner direction always has one of its components at 0
+        let (side_1, side_2) = match face {
+            Face::PositiveX | Face::NegativeX => ((0, corner_y, 0), (0, 0, corner_z)),
+            Face::PositiveY | Face::NegativeY => ((corner_x, 0, 0), (0, 0, corner_z)),
+            Face::PositiveZ | Face::NegativeZ => ((corner_x, 0, 0), (0, corner_y, 0)),
+        };
+
+        let side_1 = is_occluding(x + side_1.0, y + side_1.1, z + side_1.2);
+        let side_2 = is_occluding(x + side_2.0, y + side_2.1, z + side_2.2);
+        if side_1 && side_2 {
+            return 0;
+        }
+        let corner = is_occluding(x + corner_x, y + corner_y, z + corner_z);
+        NO_AMBIENT_OCCLUSION - (side_1 as u8 + side_2 as u8 + corner as u8)
+    })
+}
+
+// The quad is split along the diagonal between its brightest corners, otherwise
+// the occlusion is interpolated differently on the two triangles and looks wrong.
+pub fn should_flip_quad(ambient_occlusion: &FaceAmbientOcclusion) -> bool {
+    let [corner_0, corner_1, corner_2, corner_3] = *ambient_occlusion;
+    corner_0 + corner_2 < corner_1 + corner_3
+}
@@ -1,41 +1,43 @@
+use crate::game::world::block_registry::BlockRegistry;
 use bevy::prelude::*;
 use blocks_macro::create_blocks;
 
-pub fn block_is_transparent(block_id: u16) -> bool {
-    BLOCKS[block_id as usize].is_transparent
-}
-
-pub fn block_color(block_id: u16) -> Option<Color> {
-    BLOCKS[block_id as usize].color
-}
-
+// the built in blocks, their properties are read through the BlockRegistry since
+// its definitions file can change them, the accessors like block_is_transparent use it
+// every property is optional :
+// is_transparent (false), color (None), is_solid (true), is_fluid (false),
+// light_level (0), hardness (1.0), render_layer (Opaque), orientation_mode (Fixed)
 create_blocks!(
     AIR_BLOCK: {
         is_transparent: true,
-        color: None
+        is_solid: false,
+        hardness: 0.0
     },
     DEBUG_BLOCK: {
-        is_transparent: false,
         color: Some(Color::hsla(305., 1., 0.5, 1.))
     },
     DIRT_BLOCK: {
-        is_transparent: false,
-        color: Some(Color::hsla(42., 0.82, 0.3, 1.))
+        color: Some(Color::hsla(42., 0.82, 0.3, 1.)),
+        hardness: 0.5
     },
     GRASS_BLOCK: {
-        is_transparent: false,
-        color: Some(Color::hsla(127., 0.8, 0.39, 1.))
+        color: Some(Color::hsla(127., 0.8, 0.39, 1.)),
+        hardness: 0.6
     },
     STONE_BLOCK: {
-        is_transparent: false,
-        color: Some(Color::hsla(195., 0.02, 0.46, 1.))
+        color: Some(Color::hsla(195., 0.02, 0.46, 1.)),
+        hardness: 1.5
     },
     SAND_BLOCK: {
-        is_transparent: false,
-        color: Some(Color::hsla(64., 0.63, 0.62, 1.))
+        color: Some(Color::hsla(64., 0.63, 0.62, 1.)),
+        hardness: 0.5
     },
     WATER_BLOCK: {
         is_transparent: true,
-        color: Some(Color::hsla(204., 0.86, 0.48, 0.3))
+        color: Some(Color::hsla(204., 0.86, 0.48, 0.3)),
+        is_solid: false,
+        is_fluid: true,
+        hardness: 100.0,
+        render_layer: Translucent
     }
 );
@@ -0,0 +1,66 @@
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::render::mesh::{MeshVertexAttribute, MeshVertexBufferLayout};
+use bevy::render::render_resource::{
+    AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, VertexFormat,
+};
+
+const CHUNK_SHADER_PATH: &str = "shaders/chunk.wgsl";
+
+// the uv of the top left corner of the atlas tile used by the face of the vertex,
+// the UV_0 attribute hold the position on the face in blocks so the tile can be
+// repeated on greedy merged faces
+pub const ATTRIBUTE_TILE_ORIGIN: MeshVertexAttribute =
+    MeshVertexAttribute::new("TileOrigin", 720_413_596, VertexFormat::Float32x2);
+
+#[derive(AsBindGroup, Debug, Clone, TypeUuid)]
+#[uuid = "3f1c2a64-5b7e-4d8a-9c21-6e0f4b8d7a15"]
+pub struct ChunkMaterial {
+    // the size of a tile in the atlas, in uv coordinates
+    #[uniform(0)]
+    pub tile_size: Vec2,
+    #[texture(1)]
+    #[sampler(2)]
+    pub atlas: Handle<Image>,
+    // the translucent faces are blended and sorted from back to front
+    pub alpha_mode: AlphaMode,
+}
+
+impl Material for ChunkMaterial {
+    fn vertex_shader() -> ShaderRef {
+        CHUNK_SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        CHUNK_SHADER_PATH.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+            ATTRIBUTE_TILE_ORIGIN.at_shader_location(3),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(4),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+// the materials shared by all the chunks meshes
+#[derive(Resource)]
+pub struct ChunkMaterials {
+    pub opaque: Handle<ChunkMaterial>,
+    pub translucent: Handle<ChunkMaterial>,
+}
@@ -1,203 +1,297 @@
-use super::blocks_resources::block_is_transparent;
-use super::chunk::ChunkData;
 use super::pre_mesh_creator::ChunkPreMeshOneDirection;
-use super::pre_mesh_creator::{NB_BLOCK_ON_CHUNK_SIDE, NB_BLOCK_ON_CHUNK_WITHOUT_ONE_SIDE};
+use super::pre_mesh_creator::{NB_BLOCK_ON_SECTION_SIDE, NB_BLOCK_ON_SECTION_WITHOUT_ONE_SIDE};
+use super::renderer::Face;
 use crate::game::world::block::Block;
-use crate::game::world::chunk::CHUNK_SIZE;
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk::{CHUNK_SIZE, SECTION_HEIGHT};
+use crate::game::world::chunk_section::{ChunkSection, NB_BLOCKS_PER_SECTION};
 
 pub fn pre_mesh_block_faces_on_positive_x(
     pre_mesh_data: &mut ChunkPreMeshOneDirection,
-    neighbor_chunk_on_positive_x: &Option<ChunkData>,
+    neighbor_section_on_positive_x: Option<&ChunkSection>,
+    block_registry: &BlockRegistry,
 ) {
-    match neighbor_chunk_on_positive_x {
-        Some(chunk_data) => pre_mesh_positive_x_chunk_side(pre_mesh_data, chunk_data),
+    match neighbor_section_on_positive_x {
+        Some(section) => pre_mesh_positive_x_section_side(pre_mesh_data, section, block_registry),
         None => (),
     }
 
-    let array_offset = NB_BLOCK_ON_CHUNK_SIDE;
+    let array_offset = NB_BLOCK_ON_SECTION_SIDE;
 
-    for i in 0..NB_BLOCK_ON_CHUNK_WITHOUT_ONE_SIDE {
+    for i in 0..NB_BLOCK_ON_SECTION_WITHOUT_ONE_SIDE {
         let neighbor_index = i + array_offset;
         let neighbor_block = pre_mesh_data.get_pre_mesh(neighbor_index).get_block();
         let current_index = i;
         let current_block = pre_mesh_data.get_pre_mesh(current_index).get_block();
 
-        if !face_should_be_rendered(&current_block, &neighbor_block) {
+        if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
             pre_mesh_data.should_not_be_rendered(current_index);
         }
     }
 }
 
-fn pre_mesh_positive_x_chunk_side(
-    current_chunk: &mut ChunkPreMeshOneDirection,
-    neighbor_chunk: &ChunkData,
+pub fn pre_mesh_positive_x_section_side(
+    current_section: &mut ChunkPreMeshOneDirection,
+    neighbor_section: &ChunkSection,
+    block_registry: &BlockRegistry,
 ) {
-    let array_offset = NB_BLOCK_ON_CHUNK_WITHOUT_ONE_SIDE;
-    for i in 0..NB_BLOCK_ON_CHUNK_SIDE {
+    let array_offset = NB_BLOCK_ON_SECTION_WITHOUT_ONE_SIDE;
+    for i in 0..NB_BLOCK_ON_SECTION_SIDE {
         let neighbor_index = i;
-        let neighbor_block = neighbor_chunk.get_block(neighbor_index);
+        let neighbor_block = neighbor_section.get_block(neighbor_index);
         let current_index = array_offset + i;
-        let current_block = current_chunk.get_pre_mesh(current_index).get_block();
+        let current_block = current_section.get_pre_mesh(current_index).get_block();
 
-        if !face_should_be_rendered(&current_block, &neighbor_block) {
-            current_chunk.should_not_be_rendered(current_index);
+        if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
+            current_section.should_not_be_rendered(current_index);
         }
     }
 }
 
 pub fn pre_mesh_block_faces_on_negative_x(
     pre_mesh_data: &mut ChunkPreMeshOneDirection,
-    neighbor_chunk_on_negative_x: &Option<ChunkData>,
+    neighbor_section_on_negative_x: Option<&ChunkSection>,
+    block_registry: &BlockRegistry,
 ) {
-    match neighbor_chunk_on_negative_x {
-        Some(neighbor_chunk) => pre_mesh_negative_x_chunk_side(pre_mesh_data, neighbor_chunk),
+    match neighbor_section_on_negative_x {
+        Some(section) => pre_mesh_negative_x_section_side(pre_mesh_data, section, block_registry),
         None => (),
     }
 
-    let array_offset = NB_BLOCK_ON_CHUNK_SIDE;
-    for i in 0..NB_BLOCK_ON_CHUNK_WITHOUT_ONE_SIDE {
+    let array_offset = NB_BLOCK_ON_SECTION_SIDE;
+    for i in 0..NB_BLOCK_ON_SECTION_WITHOUT_ONE_SIDE {
         let neighbor_index = i;
         let neighbor_block = pre_mesh_data.get_pre_mesh(neighbor_index).get_block();
         let current_index = i + array_offset;
         let current_block = pre_mesh_data.get_pre_mesh(current_index).get_block();
 
-        if !face_should_be_rendered(&current_block, &neighbor_block) {
+        if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
             pre_mesh_data.should_not_be_rendered(current_index);
         }
     }
 }
 
-fn pre_mesh_negative_x_chunk_side(
-    current_chunk: &mut ChunkPreMeshOneDirection,
-    neighbor_chunk: &ChunkData,
+pub fn pre_mesh_negative_x_section_side(
+    current_section: &mut ChunkPreMeshOneDirection,
+    neighbor_section: &ChunkSection,
+    block_registry: &BlockRegistry,
 ) {
-    let array_offset = NB_BLOCK_ON_CHUNK_WITHOUT_ONE_SIDE;
-    for i in 0..NB_BLOCK_ON_CHUNK_SIDE {
+    let array_offset = NB_BLOCK_ON_SECTION_WITHOUT_ONE_SIDE;
+    for i in 0..NB_BLOCK_ON_SECTION_SIDE {
         let neighbor_index = i + array_offset;
-        let neighbor_block = neighbor_chunk.get_block(neighbor_index);
+        let neighbor_block = neighbor_section.get_block(neighbor_index);
         let current_index = i;
-        let current_block = current_chunk.get_pre_mesh(current_index).get_block();
+        let current_block = current_section.get_pre_mesh(current_index).get_block();
 
-        if !face_should_be_rendered(&current_block, &neighbor_block) {
-            current_chunk.should_not_be_rendered(current_index);
+        if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
+            current_section.should_not_be_rendered(current_index);
         }
     }
 }
 
 pub fn pre_mesh_block_faces_on_positive_z(
     pre_mesh_data: &mut ChunkPreMeshOneDirection,
-    neighbor_chunk_on_positive_z: &Option<ChunkData>,
+    neighbor_section_on_positive_z: Option<&ChunkSection>,
+    block_registry: &BlockRegistry,
 ) {
-    match neighbor_chunk_on_positive_z {
-        Some(chunk_data) => pre_mesh_positive_z_chunk_side(pre_mesh_data, chunk_data),
+    match neighbor_section_on_positive_z {
+        Some(section) => pre_mesh_positive_z_section_side(pre_mesh_data, section, block_registry),
         None => (),
     }
 
-    for i in 0..NB_BLOCK_ON_CHUNK_SIDE {
+    for i in 0..NB_BLOCK_ON_SECTION_SIDE {
         for k in 0..(CHUNK_SIZE - 1) {
             let neighbor_index = i * CHUNK_SIZE + k + 1;
             let neighbor_block = pre_mesh_data.get_pre_mesh(neighbor_index).get_block();
             let current_index = i * CHUNK_SIZE + k;
             let current_block = pre_mesh_data.get_pre_mesh(current_index).get_block();
 
-            if !face_should_be_rendered(&current_block, &neighbor_block) {
+            if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
                 pre_mesh_data.should_not_be_rendered(current_index);
             }
         }
     }
 }
 
-fn pre_mesh_positive_z_chunk_side(
-    current_chunk: &mut ChunkPreMeshOneDirection,
-    neighbor_chunk: &ChunkData,
+pub fn pre_mesh_positive_z_section_side(
+    current_section: &mut ChunkPreMeshOneDirection,
+    neighbor_section: &ChunkSection,
+    block_registry: &BlockRegistry,
 ) {
-    for i in 0..NB_BLOCK_ON_CHUNK_SIDE {
+    for i in 0..NB_BLOCK_ON_SECTION_SIDE {
         let neighbor_index = i * CHUNK_SIZE;
-        let neighbor_block = neighbor_chunk.get_block(neighbor_index);
+        let neighbor_block = neighbor_section.get_block(neighbor_index);
         let current_index = i * CHUNK_SIZE + CHUNK_SIZE - 1;
-        let current_block = current_chunk.get_pre_mesh(current_index).get_block();
+        let current_block = current_section.get_pre_mesh(current_index).get_block();
 
-        if !face_should_be_rendered(&current_block, &neighbor_block) {
-            current_chunk.should_not_be_rendered(current_index);
+        if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
+            current_section.should_not_be_rendered(current_index);
         }
     }
 }
 
 pub fn pre_mesh_block_faces_on_negative_z(
     pre_mesh_data: &mut ChunkPreMeshOneDirection,
-    neighbor_chunk_on_negative_z: &Option<ChunkData>,
+    neighbor_section_on_negative_z: Option<&ChunkSection>,
+    block_registry: &BlockRegistry,
 ) {
-    match neighbor_chunk_on_negative_z {
-        Some(neighbor_chunk) => pre_mesh_negative_z_chunk_side(pre_mesh_data, neighbor_chunk),
+    match neighbor_section_on_negative_z {
+        Some(section) => pre_mesh_negative_z_section_side(pre_mesh_data, section, block_registry),
         None => (),
     }
 
-    for i in 0..NB_BLOCK_ON_CHUNK_SIDE {
+    for i in 0..NB_BLOCK_ON_SECTION_SIDE {
         for k in 1..(CHUNK_SIZE) {
             let neighbor_index = i * CHUNK_SIZE + k - 1;
             let neighbor_block = pre_mesh_data.get_pre_mesh(neighbor_index).get_block();
             let current_index = i * CHUNK_SIZE + k;
             let current_block = pre_mesh_data.get_pre_mesh(current_index).get_block();
 
-            if !face_should_be_rendered(&current_block, &neighbor_block) {
+            if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
                 pre_mesh_data.should_not_be_rendered(current_index);
             }
         }
     }
 }
 
-fn pre_mesh_negative_z_chunk_side(
-    current_chunk: &mut ChunkPreMeshOneDirection,
-    neighbor_chunk: &ChunkData,
+pub fn pre_mesh_negative_z_section_side(
+    current_section: &mut ChunkPreMeshOneDirection,
+    neighbor_section: &ChunkSection,
+    block_registry: &BlockRegistry,
 ) {
-    for i in 0..NB_BLOCK_ON_CHUNK_SIDE {
+    for i in 0..NB_BLOCK_ON_SECTION_SIDE {
         let neighbor_index = i * CHUNK_SIZE + CHUNK_SIZE - 1;
-        let neighbor_block = neighbor_chunk.get_block(neighbor_index);
+        let neighbor_block = neighbor_section.get_block(neighbor_index);
         let current_index = i * CHUNK_SIZE;
-        let current_block = current_chunk.get_pre_mesh(current_index).get_block();
+        let current_block = current_section.get_pre_mesh(current_index).get_block();
 
-        if !face_should_be_rendered(&current_block, &neighbor_block) {
-            current_chunk.should_not_be_rendered(current_index);
+        if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
+            current_section.should_not_be_rendered(current_index);
         }
     }
 }
 
-pub fn pre_mesh_block_faces_on_positive_y(pre_mesh_data: &mut ChunkPreMeshOneDirection) {
+pub fn pre_mesh_block_faces_on_positive_y(
+    pre_mesh_data: &mut ChunkPreMeshOneDirection,
+    neighbor_section_on_positive_y: Option<&ChunkSection>,
+    block_registry: &BlockRegistry,
+) {
+    match neighbor_section_on_positive_y {
+        Some(section) => pre_mesh_positive_y_section_side(pre_mesh_data, section, block_registry),
+        None => (),
+    }
+
     for i in 0..CHUNK_SIZE {
-        for k in 0..(NB_BLOCK_ON_CHUNK_SIDE - CHUNK_SIZE) {
-            let neighbor_index = i * NB_BLOCK_ON_CHUNK_SIDE + k + CHUNK_SIZE;
+        for k in 0..(NB_BLOCK_ON_SECTION_SIDE - CHUNK_SIZE) {
+            let neighbor_index = i * NB_BLOCK_ON_SECTION_SIDE + k + CHUNK_SIZE;
             let neighbor_block = pre_mesh_data.get_pre_mesh(neighbor_index).get_block();
-            let current_index = i * NB_BLOCK_ON_CHUNK_SIDE + k;
+            let current_index = i * NB_BLOCK_ON_SECTION_SIDE + k;
             let current_block = pre_mesh_data.get_pre_mesh(current_index).get_block();
 
-            if !face_should_be_rendered(&current_block, &neighbor_block) {
+            if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
                 pre_mesh_data.should_not_be_rendered(current_index);
             }
         }
     }
 }
 
-pub fn pre_mesh_block_faces_on_negative_y(pre_mesh_data: &mut ChunkPreMeshOneDirection) {
+pub fn pre_mesh_positive_y_section_side(
+    current_section: &mut ChunkPreMeshOneDirection,
+    neighbor_section: &ChunkSection,
+    block_registry: &BlockRegistry,
+) {
+    let array_offset = NB_BLOCK_ON_SECTION_SIDE - CHUNK_SIZE;
+    for i in 0..CHUNK_SIZE {
+        for k in 0..CHUNK_SIZE {
+            let neighbor_index = i * NB_BLOCK_ON_SECTION_SIDE + k;
+            let neighbor_block = neighbor_section.get_block(neighbor_index);
+            let current_index = neighbor_index + array_offset;
+            let current_block = current_section.get_pre_mesh(current_index).get_block();
+
+            if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
+                current_section.should_not_be_rendered(current_index);
+            }
+        }
+    }
+}
+
+pub fn pre_mesh_block_faces_on_negative_y(
+    pre_mesh_data: &mut ChunkPreMeshOneDirection,
+    neighbor_section_on_negative_y: Option<&ChunkSection>,
+    block_registry: &BlockRegistry,
+) {
+    match neighbor_section_on_negative_y {
+        Some(section) => pre_mesh_negative_y_section_side(pre_mesh_data, section, block_registry),
+        None => (),
+    }
+
     for i in 0..CHUNK_SIZE {
-        for k in CHUNK_SIZE..NB_BLOCK_ON_CHUNK_SIDE {
-            let neighbor_index = i * NB_BLOCK_ON_CHUNK_SIDE + k - CHUNK_SIZE;
+        for k in CHUNK_SIZE..NB_BLOCK_ON_SECTION_SIDE {
+            let neighbor_index = i * NB_BLOCK_ON_SECTION_SIDE + k - CHUNK_SIZE;
             let neighbor_block = pre_mesh_data.get_pre_mesh(neighbor_index).get_block();
-            let current_index = i * NB_BLOCK_ON_CHUNK_SIDE + k;
+            let current_index = i * NB_BLOCK_ON_SECTION_SIDE + k;
             let current_block = pre_mesh_data.get_pre_mesh(current_index).get_block();
 
-            if !face_should_be_rendered(&current_block, &neighbor_block) {
+            if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
                 pre_mesh_data.should_not_be_rendered(current_index);
             }
         }
     }
 }
 
-fn face_should_be_rendered(current_block: &Block, neighbor_block: &Block) -> bool {
-    if !block_is_transparent(neighbor_block.get_id()) {
+pub fn pre_mesh_negative_y_section_side(
+    current_section: &mut ChunkPreMeshOneDirection,
+    neighbor_section: &ChunkSection,
+    block_registry: &BlockRegistry,
+) {
+    let array_offset = NB_BLOCK_ON_SECTION_SIDE - CHUNK_SIZE;
+    for i in 0..CHUNK_SIZE {
+        for k in 0..CHUNK_SIZE {
+            let current_index = i * NB_BLOCK_ON_SECTION_SIDE + k;
+            let current_block = current_section.get_pre_mesh(current_index).get_block();
+            let neighbor_index = current_index + array_offset;
+            let neighbor_block = neighbor_section.get_block(neighbor_index);
+
+            if !face_should_be_rendered(&current_block, &neighbor_block, block_registry) {
+                current_section.should_not_be_rendered(current_index);
+            }
+        }
+    }
+}
+
+// used for uniform sections, where a face is only visible on the side of the section it looks at
+pub fn hide_faces_not_on_section_side(pre_mesh_data: &mut ChunkPreMeshOneDirection, face: Face) {
+    for index in 0..NB_BLOCKS_PER_SECTION {
+        let x = index / NB_BLOCK_ON_SECTION_SIDE;
+        let y = (index / CHUNK_SIZE) % SECTION_HEIGHT;
+        let z = index % CHUNK_SIZE;
+
+        let is_on_side = match face {
+            Face::PositiveX => x == CHUNK_SIZE - 1,
+            Face::NegativeX => x == 0,
+            Face::PositiveY => y == SECTION_HEIGHT - 1,
+            Face::NegativeY => y == 0,
+            Face::PositiveZ => z == CHUNK_SIZE - 1,
+            Face::NegativeZ => z == 0,
+        };
+
+        if !is_on_side {
+            pre_mesh_data.should_not_be_rendered(index);
+        }
+    }
+}
+
+fn face_should_be_rendered(
+    current_block: &Block,
+    neighbor_block: &Block,
+    block_registry: &BlockRegistry,
+) -> bool {
+    if !block_registry.is_transparent(neighbor_block.get_id()) {
         return false;
     }
 
-    if current_block == neighbor_block {
+    // the faces between two blocks of water, or of any other transparent block, are never seen
+    if current_block.get_id() == neighbor_block.get_id() {
         return false;
     }
     true
@@ -0,0 +1,224 @@
+use super::ambient_occlusion::NO_AMBIENT_OCCLUSION;
+use super::pre_mesh_creator::NeighborChunks;
+use super::pre_mesh_to_bundle_conveter::{get_vertices_colors, ChunkFacesMeshes, FacesMesh};
+use super::renderer::Face;
+use super::texture_atlas::BlockTextureAtlas;
+use crate::game::world::block::{Block, MAX_FLUID_LEVEL};
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk::Chunk;
+use crate::game::world::chunk_section::NB_BLOCKS_PER_SECTION;
+use crate::game::world::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+
+// the surface of a fluid is a bit lower than the top of its block
+pub const FLUID_SURFACE_OFFSET: f32 = 0.125;
+
+// The height of the surface of a fluid in its block, a fluid under the same
+// fluid fill its whole block so there is no gap between them.
+pub fn get_fluid_height(block: Block, block_above: Option<Block>) -> f32 {
+    if block_above.is_some_and(|block_above| block_above.get_id() == block.get_id()) {
+        return 1.;
+    }
+
+    let full_height = 1. - FLUID_SURFACE_OFFSET;
+    let level_height = full_height / (MAX_FLUID_LEVEL + 1) as f32;
+    full_height - block.get_fluid_level() as f32 * level_height
+}
+
+// The fluids are not greedy meshed with the other blocks : only their surface
+// and their sides against air (or against a lower part of the same fluid) are
+// rendered, each block of fluid has its own faces.
+pub fn add_fluids_faces(
+    faces_meshes: &mut ChunkFacesMeshes,
+    chunk: &Chunk,
+    neighbor_chunks: &NeighborChunks,
+    texture_atlas: &BlockTextureAtlas,
+    block_registry: &BlockRegistry,
+) {
+    for (section_index, section) in chunk.blocks.get_sections().iter().enumerate() {
+        if section
+            .get_uniform_block()
+            .is_some_and(|block| !block_registry.is_fluid(block.get_id()))
+        {
+            continue;
+        }
+
+        for block_index in 0..NB_BLOCKS_PER_SECTION {
+            let block = section.get_block(block_index);
+            if !block_registry.is_fluid(block.get_id()) {
+                continue;
+            }
+
+            let block_coords =
+                BlockCoordsInChunk::from_section_block_index(section_index, block_index);
+            let (x, y, z) = block_coords.pos_tuple();
+            let position = (x as i32, y as i32, z as i32);
+            let render_layer = block_registry.get_render_layer(block.get_id());
+            add_fluid_faces(
+                faces_meshes.get_mut(render_layer),
+                block,
+                position,
+                chunk,
+                neighbor_chunks,
+                texture_atlas,
+                block_registry,
+            );
+        }
+    }
+}
+
+fn add_fluid_faces(
+    faces: &mut FacesMesh,
+    block: Block,
+    position: (i32, i32, i32),
+    chunk: &Chunk,
+    neighbor_chunks: &NeighborChunks,
+    texture_atlas: &BlockTextureAtlas,
+    block_registry: &BlockRegistry,
+) {
+    let (x, y, z) = position;
+    let get_block = |x, y, z| neighbor_chunks.get_block(chunk, x, y, z);
+    let is_air = |neighbor: Option<Block>| {
+        neighbor.is_some_and(|neighbor| {
+            block_registry.is_transparent(neighbor.get_id())
+                && !block_registry.is_fluid(neighbor.get_id())
+        })
+    };
+    let block_above = get_block(x, y + 1, z);
+    let height = get_fluid_height(block, block_above);
+
+    let mut faces_to_render = Vec::new();
+    if block_above.is_none_or(|block_above| block_above.get_id() != block.get_id()) {
+        faces_to_render.push((Face::PositiveY, 0., height));
+    }
+    if is_air(get_block(x, y - 1, z)) {
+        faces_to_render.push((Face::NegativeY, 0., height));
+    }
+
+    let sides = [
+        (Face::PositiveX, (1, 0, 0)),
+        (Face::NegativeX, (-1, 0, 0)),
+        (Face::PositiveZ, (0, 0, 1)),
+        (Face::NegativeZ, (0, 0, -1)),
+    ];
+    for (face, (offset_x, _, offset_z)) in sides {
+        let (neighbor_x, neighbor_z) = (x + offset_x, z + offset_z);
+        let neighbor = get_block(neighbor_x, y, neighbor_z);
+        let bottom = if is_air(neighbor) {
+            0.
+        } else if let Some(neighbor) =
+            neighbor.filter(|neighbor| neighbor.get_id() == block.get_id())
+        {
+            // only the part above the neighbor fluid can be seen
+            get_fluid_height(neighbor, get_block(neighbor_x, y + 1, neighbor_z))
+        } else {
+            continue;
+        };
+        if bottom < height {
+            faces_to_render.push((face, bottom, height));
+        }
+    }
+
+    for (face, bottom, top) in faces_to_render {
+        let (offset_x, offset_y, offset_z) = match face {
+            Face::PositiveX => (1, 0, 0),
+            Face::NegativeX => (-1, 0, 0),
+            Face::PositiveY => (0, 1, 0),
+            Face::NegativeY => (0, -1, 0),
+            Face::PositiveZ => (0, 0, 1),
+            Face::NegativeZ => (0, 0, -1),
+        };
+        let light = neighbor_chunks.get_light(chunk, x + offset_x, y + offset_y, z + offset_z);
+
+        let mut mesh = get_fluid_face(face, position, bottom, top);
+        let block_face = block_registry.get_block_face(block, face);
+        let tile_origin = texture_atlas.get_tile_origin(block.get_id(), block_face);
+        mesh.vertices_tile_origins = vec![tile_origin; 4];
+        mesh.vertices_colors = get_vertices_colors(light, &[NO_AMBIENT_OCCLUSION; 4]);
+        faces.add(&mut mesh);
+    }
+}
+
+// A face of a fluid block going from bottom to top in the block. The top and
+// bottom faces are at the top of the fluid and at the bottom of the block.
+fn get_fluid_face(face: Face, position: (i32, i32, i32), bottom: f32, top: f32) -> FacesMesh {
+    let (x, y, z) = (position.0 as f32, position.1 as f32, position.2 as f32);
+    let (bottom_y, top_y) = (y + bottom, y + top);
+    // the tile is placed like on the full blocks, its top at the top of the block
+    let (bottom_v, top_v) = (1. - bottom, 1. - top);
+
+    let mut mesh = FacesMesh::new();
+    let (vertices, normal, uv, indices) = match face {
+        Face::PositiveX => (
+            [
+                [x + 1., top_y, z + 1.],
+                [x + 1., bottom_y, z + 1.],
+                [x + 1., bottom_y, z],
+                [x + 1., top_y, z],
+            ],
+            [1., 0., 0.],
+            [[0., top_v], [0., bottom_v], [1., bottom_v], [1., top_v]],
+            [0, 1, 2, 0, 2, 3],
+        ),
+        Face::NegativeX => (
+            [
+                [x, top_y, z + 1.],
+                [x, bottom_y, z + 1.],
+                [x, bottom_y, z],
+                [x, top_y, z],
+            ],
+            [-1., 0., 0.],
+            [[1., top_v], [1., bottom_v], [0., bottom_v], [0., top_v]],
+            [0, 2, 1, 0, 3, 2],
+        ),
+        Face::PositiveY => (
+            [
+                [x + 1., top_y, z + 1.],
+                [x, top_y, z + 1.],
+                [x, top_y, z],
+                [x + 1., top_y, z],
+            ],
+            [0., 1., 0.],
+            [[1., 1.], [0., 1.], [0., 0.], [1., 0.]],
+            [0, 2, 1, 0, 3, 2],
+        ),
+        Face::NegativeY => (
+            [
+                [x + 1., y, z + 1.],
+                [x, y, z + 1.],
+                [x, y, z],
+                [x + 1., y, z],
+            ],
+            [0., -1., 0.],
+            [[1., 1.], [0., 1.], [0., 0.], [1., 0.]],
+            [0, 1, 2, 0, 2, 3],
+        ),
+        Face::PositiveZ => (
+            [
+                [x + 1., top_y, z + 1.],
+                [x + 1., bottom_y, z + 1.],
+                [x, bottom_y, z + 1.],
+                [x, top_y, z + 1.],
+            ],
+            [0., 0., 1.],
+            [[1., top_v], [1., bottom_v], [0., bottom_v], [0., top_v]],
+            [0, 2, 1, 0, 3, 2],
+        ),
+        Face::NegativeZ => (
+            [
+                [x + 1., top_y, z],
+                [x + 1., bottom_y, z],
+                [x, bottom_y, z],
+                [x, top_y, z],
+            ],
+            [0., 0., -1.],
+            [[0., top_v], [0., bottom_v], [1., bottom_v], [1., top_v]],
+            [0, 1, 2, 0, 2, 3],
+        ),
+    };
+
+    mesh.vertices = vertices.to_vec();
+    mesh.vertices_normals = vec![normal; 4];
+    mesh.vertices_uv = uv.to_vec();
+    mesh.indices = indices.to_vec();
+    mesh
+}
@@ -1,13 +1,14 @@
-use super::chunk::{CHUNK_SIZE, NB_BLOCKS_PER_CHUNK};
+use super::chunk::CHUNK_SIZE;
 use super::pre_mesh_creator::{
-    BlockPreMesh, ChunkPreMeshOneDirection, Stretch, NB_BLOCK_ON_CHUNK_SIDE,
+    BlockPreMesh, ChunkPreMeshOneDirection, Stretch, NB_BLOCK_ON_SECTION_SIDE,
 };
+use crate::game::world::chunk_section::NB_BLOCKS_PER_SECTION;
 
 pub fn merge_faces_on_x_axis(
     positive_x_pre_mesh: &mut ChunkPreMeshOneDirection,
     negative_x_pre_mesh: &mut ChunkPreMeshOneDirection,
 ) {
-    for i in 0..NB_BLOCKS_PER_CHUNK {
+    for i in 0..NB_BLOCKS_PER_SECTION {
         if i % CHUNK_SIZE == 0 {
             continue;
         }
@@ -16,8 +17,8 @@ pub fn merge_faces_on_x_axis(
         merge_faces_on_x_axis_along_z(negative_x_pre_mesh, i);
     }
 
-    for i in 0..NB_BLOCKS_PER_CHUNK {
-        if i % NB_BLOCK_ON_CHUNK_SIDE < CHUNK_SIZE {
+    for i in 0..NB_BLOCKS_PER_SECTION {
+        if i % NB_BLOCK_ON_SECTION_SIDE < CHUNK_SIZE {
             continue;
         }
 
@@ -34,7 +35,7 @@ fn merge_faces_on_x_axis_along_z(pre_mesh_on_x_axis: &mut ChunkPreMeshOneDirecti
         return;
     }
 
-    if pre_mesh_1.get_block() != pre_mesh_2.get_block() {
+    if !pre_mesh_on_x_axis.have_same_appearance(index - 1, index) {
         return;
     }
 
@@ -65,7 +66,7 @@ fn merge_faces_on_x_axis_along_y(pre_mesh_on_x_axis: &mut ChunkPreMeshOneDirecti
         return;
     }
 
-    if pre_mesh_1.get_block() != pre_mesh_2.get_block() {
+    if !pre_mesh_on_x_axis.have_same_appearance(index - CHUNK_SIZE, index) {
         return;
     }
 
@@ -91,13 +92,13 @@ pub fn merge_faces_on_z_axis(
     positive_z_pre_mesh: &mut ChunkPreMeshOneDirection,
     negative_z_pre_mesh: &mut ChunkPreMeshOneDirection,
 ) {
-    for i in NB_BLOCK_ON_CHUNK_SIDE..NB_BLOCKS_PER_CHUNK {
+    for i in NB_BLOCK_ON_SECTION_SIDE..NB_BLOCKS_PER_SECTION {
         merge_faces_on_z_axis_along_x(positive_z_pre_mesh, i);
         merge_faces_on_z_axis_along_x(negative_z_pre_mesh, i);
     }
 
-    for i in 0..NB_BLOCKS_PER_CHUNK {
-        if i % NB_BLOCK_ON_CHUNK_SIDE < CHUNK_SIZE {
+    for i in 0..NB_BLOCKS_PER_SECTION {
+        if i % NB_BLOCK_ON_SECTION_SIDE < CHUNK_SIZE {
             continue;
         }
 
@@ -107,14 +108,14 @@ pub fn merge_faces_on_z_axis(
 }
 
 fn merge_faces_on_z_axis_along_x(pre_mesh_on_z_axis: &mut ChunkPreMeshOneDirection, index: usize) {
-    let pre_mesh_1 = pre_mesh_on_z_axis.get_pre_mesh(index - NB_BLOCK_ON_CHUNK_SIDE);
+    let pre_mesh_1 = pre_mesh_on_z_axis.get_pre_mesh(index - NB_BLOCK_ON_SECTION_SIDE);
     let pre_mesh_2 = pre_mesh_on_z_axis.get_pre_mesh(index);
 
     if !pre_mesh_1.will_be_rendered() || !pre_mesh_2.will_be_rendered() {
         return;
     }
 
-    if pre_mesh_1.get_block() != pre_mesh_2.get_block() {
+    if !pre_mesh_on_z_axis.have_same_appearance(index - NB_BLOCK_ON_SECTION_SIDE, index) {
         return;
     }
 
@@ -134,7 +135,7 @@ fn merge_faces_on_z_axis_along_x(pre_mesh_on_z_axis: &mut ChunkPreMeshOneDirecti
 
     let new_pre_mesh = BlockPreMesh::new(new_block, new_stretch);
     pre_mesh_on_z_axis.set_pre_mesh(index, new_pre_mesh);
-    pre_mesh_on_z_axis.should_not_be_rendered(index - NB_BLOCK_ON_CHUNK_SIDE);
+    pre_mesh_on_z_axis.should_not_be_rendered(index - NB_BLOCK_ON_SECTION_SIDE);
 }
 
 fn merge_faces_on_z_axis_along_y(pre_mesh_on_z_axis: &mut ChunkPreMeshOneDirection, index: usize) {
@@ -145,7 +146,7 @@ fn merge_faces_on_z_axis_along_y(pre_mesh_on_z_axis: &mut ChunkPreMeshOneDirecti
         return;
     }
 
-    if pre_mesh_1.get_block() != pre_mesh_2.get_block() {
+    if !pre_mesh_on_z_axis.have_same_appearance(index - CHUNK_SIZE, index) {
         return;
     }
 
@@ -171,12 +172,12 @@ pub fn merge_faces_on_y_axis(
     positive_y_pre_mesh: &mut ChunkPreMeshOneDirection,
     negative_y_pre_mesh: &mut ChunkPreMeshOneDirection,
 ) {
-    for i in NB_BLOCK_ON_CHUNK_SIDE..NB_BLOCKS_PER_CHUNK {
+    for i in NB_BLOCK_ON_SECTION_SIDE..NB_BLOCKS_PER_SECTION {
         merge_faces_on_y_axis_along_x(positive_y_pre_mesh, i);
         merge_faces_on_y_axis_along_x(negative_y_pre_mesh, i);
     }
 
-    for i in 0..NB_BLOCKS_PER_CHUNK {
+    for i in 0..NB_BLOCKS_PER_SECTION {
         if i % CHUNK_SIZE == 0 {
             continue;
         }
@@ -187,14 +188,14 @@ pub fn merge_faces_on_y_axis(
 }
 
 fn merge_faces_on_y_axis_along_x(pre_mesh_on_y_axis: &mut ChunkPreMeshOneDirection, index: usize) {
-    let pre_mesh_1 = pre_mesh_on_y_axis.get_pre_mesh(index - NB_BLOCK_ON_CHUNK_SIDE);
+    let pre_mesh_1 = pre_mesh_on_y_axis.get_pre_mesh(index - NB_BLOCK_ON_SECTION_SIDE);
     let pre_mesh_2 = pre_mesh_on_y_axis.get_pre_mesh(index);
 
     if !pre_mesh_1.will_be_rendered() || !pre_mesh_2.will_be_rendered() {
         return;
     }
 
-    if pre_mesh_1.get_block() != pre_mesh_2.get_block() {
+    if !pre_mesh_on_y_axis.have_same_appearance(index - NB_BLOCK_ON_SECTION_SIDE, index) {
         return;
     }
 
@@ -214,7 +215,7 @@ fn merge_faces_on_y_axis_along_x(pre_mesh_on_y_axis: &mut ChunkPreMeshOneDirecti
 
     let new_pre_mesh = BlockPreMesh::new(new_block, new_stretch);
     pre_mesh_on_y_axis.set_pre_mesh(index, new_pre_mesh);
-    pre_mesh_on_y_axis.should_not_be_rendered(index - NB_BLOCK_ON_CHUNK_SIDE);
+    pre_mesh_on_y_axis.should_not_be_rendered(index - NB_BLOCK_ON_SECTION_SIDE);
 }
 
 fn merge_faces_on_y_axis_along_z(pre_mesh_on_y_axis: &mut ChunkPreMeshOneDirection, index: usize) {
@@ -225,7 +226,7 @@ fn merge_faces_on_y_axis_along_z(pre_mesh_on_y_axis: &mut ChunkPreMeshOneDirecti
         return;
     }
 
-    if pre_mesh_1.get_block() != pre_mesh_2.get_block() {
+    if !pre_mesh_on_y_axis.have_same_appearance(index - 1, index) {
         return;
     }
 
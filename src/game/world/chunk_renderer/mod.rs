@@ -1,7 +1,11 @@
+pub mod ambient_occlusion;
+pub mod chunk_material;
 pub mod covered_faces;
+pub mod fluid_mesh;
 pub mod greedy_mesh;
 pub mod pre_mesh_creator;
 pub mod renderer;
+pub mod texture_atlas;
 use super::block;
 use super::chunk;
 pub mod blocks_resources;
@@ -1,11 +1,20 @@
+use super::ambient_occlusion::{
+    get_face_ambient_occlusion, FaceAmbientOcclusion, NO_AMBIENT_OCCLUSION,
+};
 use super::block::Block;
 use super::blocks_resources::blocks_ids::AIR_BLOCK;
-use super::chunk::{ChunkData, CHUNK_HEIGHT, CHUNK_SIZE, NB_BLOCKS_PER_CHUNK};
+use super::chunk::{Chunk, CHUNK_HEIGHT, CHUNK_SIZE, NB_SECTIONS_PER_CHUNK, SECTION_HEIGHT};
 use super::covered_faces::*;
 use super::greedy_mesh::*;
+use super::renderer::Face;
+use crate::game::world::block_registry::{BlockFace, BlockRegistry};
+use crate::game::world::chunk_section::{ChunkSection, NB_BLOCKS_PER_SECTION};
+use crate::game::world::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+use crate::game::world::light::chunk_light::{LightLevels, MAX_LIGHT_LEVEL};
 
-pub const NB_BLOCK_ON_CHUNK_SIDE: usize = CHUNK_SIZE * CHUNK_HEIGHT;
-pub const NB_BLOCK_ON_CHUNK_WITHOUT_ONE_SIDE: usize = NB_BLOCKS_PER_CHUNK - NB_BLOCK_ON_CHUNK_SIDE;
+pub const NB_BLOCK_ON_SECTION_SIDE: usize = CHUNK_SIZE * SECTION_HEIGHT;
+pub const NB_BLOCK_ON_SECTION_WITHOUT_ONE_SIDE: usize =
+    NB_BLOCKS_PER_SECTION - NB_BLOCK_ON_SECTION_SIDE;
 
 // the stretch contain how much a mesh should expand along the 3 axis
 // The data is stored like this
@@ -73,7 +82,7 @@ impl Stretch {
             stretch_x as f32
         };
         let y = if stretch_y == 0 {
-            CHUNK_HEIGHT as f32
+            SECTION_HEIGHT as f32
         } else {
             stretch_y as f32
         };
@@ -93,15 +102,15 @@ impl Default for Stretch {
     }
 }
 
-// this struct contain all the data needed to create an optimaized mesh of a chunk
+// this struct contain all the data needed to create an optimaized mesh of a chunk section
 // Stored like this :
-//IIII IIII IIIU UOOO XXXX YYYY YYYY ZZZZ
+//IIII IIII IIIS SOOO XXXX YYYY YYYY ZZZZ
 // 11 bits for the block ID marked as "I"
 // 3 bits for the block orientation marked as "O"
 // 4 bits for the number of blocks the mesh sould expand along the X axis marked as "X"
 // 8 bits for the number of blocks the mesh sould expand along the Y axis marked as "Y"
 // 4 bits for the number of blocks the mesh sould expand along the Z axis marked as "Z"
-// 2 bits unused marked as "U"
+// 2 bits for the state of the block marked as "S"
 #[derive(Clone, Copy, Debug)]
 pub struct BlockPreMesh(u32);
 
@@ -137,54 +146,252 @@ impl BlockPreMesh {
 }
 
 #[derive(Clone)]
-pub struct ChunkPreMeshOneDirection(Vec<BlockPreMesh>);
+pub struct ChunkPreMeshOneDirection {
+    pre_meshes: Vec<BlockPreMesh>,
+    // the face of the oriented block, the light in front of each face and the
+    // ambient occlusion of its corners, only set for the faces that will be rendered
+    block_faces: Vec<BlockFace>,
+    lights: Vec<LightLevels>,
+    ambient_occlusions: Vec<FaceAmbientOcclusion>,
+}
 
 impl ChunkPreMeshOneDirection {
-    pub fn new(chunk_data: &[Block]) -> Self {
-        let mut blocks_pre_mesh_data = Vec::with_capacity(NB_BLOCKS_PER_CHUNK);
-
-        for block in chunk_data.iter().take(NB_BLOCKS_PER_CHUNK) {
-            let mut stretch = Stretch::default();
-
-            if block.get_id() == AIR_BLOCK {
-                stretch.0 = 0;
-            }
+    pub fn new(section: &ChunkSection) -> Self {
+        let block_faces = vec![BlockFace::Side; NB_BLOCKS_PER_SECTION];
+        let lights = vec![LightLevels::default(); NB_BLOCKS_PER_SECTION];
+        let ambient_occlusions = vec![[NO_AMBIENT_OCCLUSION; 4]; NB_BLOCKS_PER_SECTION];
+        if let Some(block) = section.get_uniform_block() {
+            let pre_meshes = vec![get_default_pre_mesh(block); NB_BLOCKS_PER_SECTION];
+            return Self {
+                pre_meshes,
+                block_faces,
+                lights,
+                ambient_occlusions,
+            };
+        }
 
-            blocks_pre_mesh_data.push(BlockPreMesh::new(*block, stretch));
+        let mut pre_meshes = Vec::with_capacity(NB_BLOCKS_PER_SECTION);
+        for block_index in 0..NB_BLOCKS_PER_SECTION {
+            let block = section.get_block(block_index);
+            pre_meshes.push(get_default_pre_mesh(block));
+        }
+        Self {
+            pre_meshes,
+            block_faces,
+            lights,
+            ambient_occlusions,
         }
-        assert!(blocks_pre_mesh_data.len() == NB_BLOCKS_PER_CHUNK);
-        Self(blocks_pre_mesh_data)
     }
 
     pub fn as_slice(&self) -> &[BlockPreMesh] {
-        self.0.as_slice()
+        self.pre_meshes.as_slice()
     }
 
     pub fn as_mut_slice(&mut self) -> &mut [BlockPreMesh] {
-        self.0.as_mut_slice()
+        self.pre_meshes.as_mut_slice()
     }
 
     pub fn get_pre_mesh(&self, pre_mesh_index: usize) -> BlockPreMesh {
-        self.0[pre_mesh_index]
+        self.pre_meshes[pre_mesh_index]
     }
 
     pub fn set_pre_mesh(&mut self, pre_mesh_index: usize, value: BlockPreMesh) {
-        self.0[pre_mesh_index] = value;
+        self.pre_meshes[pre_mesh_index] = value;
     }
 
     pub fn should_not_be_rendered(&mut self, pre_mesh_index: usize) {
-        self.0[pre_mesh_index].should_not_be_rendered();
+        self.pre_meshes[pre_mesh_index].should_not_be_rendered();
+    }
+
+    pub fn get_block_face(&self, pre_mesh_index: usize) -> BlockFace {
+        self.block_faces[pre_mesh_index]
+    }
+
+    pub fn get_light(&self, pre_mesh_index: usize) -> LightLevels {
+        self.lights[pre_mesh_index]
+    }
+
+    // the fluids have their own mesh, see fluid_mesh
+    fn hide_fluids_faces(&mut self, block_registry: &BlockRegistry) {
+        for pre_mesh_index in 0..NB_BLOCKS_PER_SECTION {
+            let block = self.pre_meshes[pre_mesh_index].get_block();
+            if block_registry.is_fluid(block.get_id()) {
+                self.should_not_be_rendered(pre_mesh_index);
+            }
+        }
+    }
+
+    pub fn get_ambient_occlusion(&self, pre_mesh_index: usize) -> FaceAmbientOcclusion {
+        self.ambient_occlusions[pre_mesh_index]
+    }
+
+    // two faces can only be merged if they look the same, so the merged face
+    // doesn't spread the texture, the light or the occlusion of one block over the
+    // others. Two blocks oriented differently look the same when they show the same face.
+    pub fn have_same_appearance(&self, pre_mesh_index_1: usize, pre_mesh_index_2: usize) -> bool {
+        self.get_pre_mesh(pre_mesh_index_1).get_block().get_id()
+            == self.get_pre_mesh(pre_mesh_index_2).get_block().get_id()
+            && self.block_faces[pre_mesh_index_1] == self.block_faces[pre_mesh_index_2]
+            && self.lights[pre_mesh_index_1] == self.lights[pre_mesh_index_2]
+            && self.ambient_occlusions[pre_mesh_index_1]
+                == self.ambient_occlusions[pre_mesh_index_2]
+    }
+
+    // the faces show the face of their oriented block, they are lit by the block in
+    // front of them and occluded by the blocks around it
+    fn set_faces_appearance(
+        &mut self,
+        face: Face,
+        section_index: usize,
+        chunk: &Chunk,
+        neighbor_chunks: &NeighborChunks,
+        block_registry: &BlockRegistry,
+    ) {
+        let (offset_x, offset_y, offset_z) = match face {
+            Face::PositiveX => (1, 0, 0),
+            Face::NegativeX => (-1, 0, 0),
+            Face::PositiveY => (0, 1, 0),
+            Face::NegativeY => (0, -1, 0),
+            Face::PositiveZ => (0, 0, 1),
+            Face::NegativeZ => (0, 0, -1),
+        };
+
+        for pre_mesh_index in 0..NB_BLOCKS_PER_SECTION {
+            if !self.pre_meshes[pre_mesh_index].will_be_rendered() {
+                continue;
+            }
+            let block = self.pre_meshes[pre_mesh_index].get_block();
+            self.block_faces[pre_mesh_index] = block_registry.get_block_face(block, face);
+
+            let block_coords =
+                BlockCoordsInChunk::from_section_block_index(section_index, pre_mesh_index);
+            let (x, y, z) = block_coords.pos_tuple();
+            let front_x = x as i32 + offset_x;
+            let front_y = y as i32 + offset_y;
+            let front_z = z as i32 + offset_z;
+            self.lights[pre_mesh_index] =
+                neighbor_chunks.get_light(chunk, front_x, front_y, front_z);
+
+            let is_occluding = |x, y, z| {
+                neighbor_chunks
+                    .get_block(chunk, x, y, z)
+                    .is_some_and(|block| !block_registry.is_transparent(block.get_id()))
+            };
+            self.ambient_occlusions[pre_mesh_index] =
+                get_face_ambient_occlusion(face, (front_x, front_y, front_z), is_occluding);
+        }
     }
 }
 
-pub struct NeighborChunks {
-    pub positive_x: Option<ChunkData>,
-    pub negative_x: Option<ChunkData>,
-    pub positive_z: Option<ChunkData>,
-    pub negative_z: Option<ChunkData>,
+fn get_default_pre_mesh(block: Block) -> BlockPreMesh {
+    let mut stretch = Stretch::default();
+    if block.get_id() == AIR_BLOCK {
+        stretch.0 = 0;
+    }
+    BlockPreMesh::new(block, stretch)
+}
+
+pub struct NeighborChunks<'a> {
+    pub positive_x: Option<&'a Chunk>,
+    pub negative_x: Option<&'a Chunk>,
+    pub positive_z: Option<&'a Chunk>,
+    pub negative_z: Option<&'a Chunk>,
+}
+
+impl<'a> NeighborChunks<'a> {
+    // the sections touching the section at section_index of chunk,
+    // above and under it are the sections of the same chunk
+    pub fn get_neighbor_sections(
+        &self,
+        chunk: &'a Chunk,
+        section_index: usize,
+    ) -> NeighborSections<'a> {
+        let chunk_data = &chunk.blocks;
+        let get_section =
+            |chunk: Option<&'a Chunk>| chunk.map(|chunk| chunk.blocks.get_section(section_index));
+
+        NeighborSections {
+            positive_x: get_section(self.positive_x),
+            negative_x: get_section(self.negative_x),
+            positive_y: (section_index + 1 < NB_SECTIONS_PER_CHUNK)
+                .then(|| chunk_data.get_section(section_index + 1)),
+            negative_y: (section_index > 0).then(|| chunk_data.get_section(section_index - 1)),
+            positive_z: get_section(self.positive_z),
+            negative_z: get_section(self.negative_z),
+        }
+    }
+
+    // The chunk holding a block next to the chunk, x and z can be one block outside of it.
+    // None for the chunks not loaded yet and for the blocks in the diagonal chunks.
+    fn get_chunk_and_block_index(
+        &self,
+        chunk: &'a Chunk,
+        x: i32,
+        y: i32,
+        z: i32,
+    ) -> Option<(&'a Chunk, usize)> {
+        let chunk_size = CHUNK_SIZE as i32;
+        let is_outside_on_x = !(0..chunk_size).contains(&x);
+        let is_outside_on_z = !(0..chunk_size).contains(&z);
+        if is_outside_on_x && is_outside_on_z {
+            return None;
+        }
+
+        let (chunk, x, z) = if x < 0 {
+            (self.negative_x, x + chunk_size, z)
+        } else if x >= chunk_size {
+            (self.positive_x, x - chunk_size, z)
+        } else if z < 0 {
+            (self.negative_z, x, z + chunk_size)
+        } else if z >= chunk_size {
+            (self.positive_z, x, z - chunk_size)
+        } else {
+            (Some(chunk), x, z)
+        };
+
+        let block_coords = BlockCoordsInChunk::new(x as usize, y as usize, z as usize);
+        Some((chunk?, block_coords.get_block_index()))
+    }
+
+    // Above the world and in the chunks not loaded yet there is only the sky light.
+    pub fn get_light(&self, chunk: &'a Chunk, x: i32, y: i32, z: i32) -> LightLevels {
+        let sky_light = LightLevels::new(MAX_LIGHT_LEVEL, 0);
+        if y >= CHUNK_HEIGHT as i32 {
+            return sky_light;
+        }
+        if y < 0 {
+            return LightLevels::default();
+        }
+
+        match self.get_chunk_and_block_index(chunk, x, y, z) {
+            Some((chunk, block_index)) => chunk.light.get(block_index),
+            None => sky_light,
+        }
+    }
+
+    // None outside of the world and when the block is not loaded
+    pub fn get_block(&self, chunk: &'a Chunk, x: i32, y: i32, z: i32) -> Option<Block> {
+        if !(0..CHUNK_HEIGHT as i32).contains(&y) {
+            return None;
+        }
+        let (chunk, block_index) = self.get_chunk_and_block_index(chunk, x, y, z)?;
+        Some(chunk.get_block(block_index))
+    }
+}
+
+pub struct NeighborSections<'a> {
+    pub positive_x: Option<&'a ChunkSection>,
+    pub negative_x: Option<&'a ChunkSection>,
+    pub positive_y: Option<&'a ChunkSection>,
+    pub negative_y: Option<&'a ChunkSection>,
+    pub positive_z: Option<&'a ChunkSection>,
+    pub negative_z: Option<&'a ChunkSection>,
 }
 
 pub struct ChunkPreMesh {
+    pub section_index: usize,
+    // every face inside a uniform section is covered, only its sides have to be checked
+    is_uniform: bool,
     pub positive_x: ChunkPreMeshOneDirection,
     pub negative_x: ChunkPreMeshOneDirection,
     pub positive_y: ChunkPreMeshOneDirection,
@@ -194,8 +401,8 @@ pub struct ChunkPreMesh {
 }
 
 impl ChunkPreMesh {
-    pub fn new(chunk_data: &[Block]) -> Self {
-        let positive_x = ChunkPreMeshOneDirection::new(chunk_data);
+    pub fn new(section: &ChunkSection, section_index: usize) -> Self {
+        let positive_x = ChunkPreMeshOneDirection::new(section);
         let negative_x = positive_x.clone();
         let positive_y = positive_x.clone();
         let negative_y = positive_x.clone();
@@ -203,6 +410,8 @@ impl ChunkPreMesh {
         let negative_z = positive_x.clone();
 
         Self {
+            section_index,
+            is_uniform: section.get_uniform_block().is_some(),
             positive_x,
             negative_x,
             positive_y,
@@ -212,18 +421,128 @@ impl ChunkPreMesh {
         }
     }
 
-    pub fn optimise(&mut self, neighbor_chunks: &NeighborChunks) {
-        self.hide_covered_faces(neighbor_chunks);
+    pub fn optimise(
+        &mut self,
+        chunk: &Chunk,
+        neighbor_chunks: &NeighborChunks,
+        block_registry: &BlockRegistry,
+    ) {
+        let neighbor_sections = neighbor_chunks.get_neighbor_sections(chunk, self.section_index);
+        if self.is_uniform {
+            self.hide_inner_faces();
+            self.hide_covered_faces_on_sides(&neighbor_sections, block_registry);
+        } else {
+            self.hide_covered_faces(&neighbor_sections, block_registry);
+        }
+        self.hide_fluids_faces(block_registry);
+        self.set_faces_appearance(chunk, neighbor_chunks, block_registry);
         self.apply_greedy_meshing();
     }
 
-    fn hide_covered_faces(&mut self, neighbor_chunks: &NeighborChunks) {
-        pre_mesh_block_faces_on_positive_x(&mut self.positive_x, &neighbor_chunks.positive_x);
-        pre_mesh_block_faces_on_negative_x(&mut self.negative_x, &neighbor_chunks.negative_x);
-        pre_mesh_block_faces_on_positive_y(&mut self.positive_y);
-        pre_mesh_block_faces_on_negative_y(&mut self.negative_y);
-        pre_mesh_block_faces_on_positive_z(&mut self.positive_z, &neighbor_chunks.positive_z);
-        pre_mesh_block_faces_on_negative_z(&mut self.negative_z, &neighbor_chunks.negative_z);
+    fn hide_fluids_faces(&mut self, block_registry: &BlockRegistry) {
+        self.positive_x.hide_fluids_faces(block_registry);
+        self.negative_x.hide_fluids_faces(block_registry);
+        self.positive_y.hide_fluids_faces(block_registry);
+        self.negative_y.hide_fluids_faces(block_registry);
+        self.positive_z.hide_fluids_faces(block_registry);
+        self.negative_z.hide_fluids_faces(block_registry);
+    }
+
+    fn set_faces_appearance(
+        &mut self,
+        chunk: &Chunk,
+        neighbor_chunks: &NeighborChunks,
+        block_registry: &BlockRegistry,
+    ) {
+        let section_index = self.section_index;
+        let directions = [
+            (&mut self.positive_x, Face::PositiveX),
+            (&mut self.negative_x, Face::NegativeX),
+            (&mut self.positive_y, Face::PositiveY),
+            (&mut self.negative_y, Face::NegativeY),
+            (&mut self.positive_z, Face::PositiveZ),
+            (&mut self.negative_z, Face::NegativeZ),
+        ];
+        for (pre_mesh_in_a_direction, face) in directions {
+            pre_mesh_in_a_direction.set_faces_appearance(
+                face,
+                section_index,
+                chunk,
+                neighbor_chunks,
+                block_registry,
+            );
+        }
+    }
+
+    fn hide_covered_faces(
+        &mut self,
+        neighbor_sections: &NeighborSections,
+        block_registry: &BlockRegistry,
+    ) {
+        pre_mesh_block_faces_on_positive_x(
+            &mut self.positive_x,
+            neighbor_sections.positive_x,
+            block_registry,
+        );
+        pre_mesh_block_faces_on_negative_x(
+            &mut self.negative_x,
+            neighbor_sections.negative_x,
+            block_registry,
+        );
+        pre_mesh_block_faces_on_positive_y(
+            &mut self.positive_y,
+            neighbor_sections.positive_y,
+            block_registry,
+        );
+        pre_mesh_block_faces_on_negative_y(
+            &mut self.negative_y,
+            neighbor_sections.negative_y,
+            block_registry,
+        );
+        pre_mesh_block_faces_on_positive_z(
+            &mut self.positive_z,
+            neighbor_sections.positive_z,
+            block_registry,
+        );
+        pre_mesh_block_faces_on_negative_z(
+            &mut self.negative_z,
+            neighbor_sections.negative_z,
+            block_registry,
+        );
+    }
+
+    fn hide_inner_faces(&mut self) {
+        hide_faces_not_on_section_side(&mut self.positive_x, Face::PositiveX);
+        hide_faces_not_on_section_side(&mut self.negative_x, Face::NegativeX);
+        hide_faces_not_on_section_side(&mut self.positive_y, Face::PositiveY);
+        hide_faces_not_on_section_side(&mut self.negative_y, Face::NegativeY);
+        hide_faces_not_on_section_side(&mut self.positive_z, Face::PositiveZ);
+        hide_faces_not_on_section_side(&mut self.negative_z, Face::NegativeZ);
+    }
+
+    fn hide_covered_faces_on_sides(
+        &mut self,
+        neighbor_sections: &NeighborSections,
+        block_registry: &BlockRegistry,
+    ) {
+        if let Some(section) = neighbor_sections.positive_x {
+            pre_mesh_positive_x_section_side(&mut self.positive_x, section, block_registry);
+        }
+        if let Some(section) = neighbor_sections.negative_x {
+            pre_mesh_negative_x_section_side(&mut self.negative_x, section, block_registry);
+        }
+        if let Some(section) = neighbor_sections.positive_y {
+            pre_mesh_positive_y_section_side(&mut self.positive_y, section, block_registry);
+        }
+        if let Some(section) = neighbor_sections.negative_y {
+            pre_mesh_negative_y_section_side(&mut self.negative_y, section, block_registry);
+        }
+        if let Some(section) = neighbor_sections.positive_z {
+            pre_mesh_positive_z_section_side(&mut self.positive_z, section, block_registry);
+        }
+        if let Some(section) = neighbor_sections.negative_z {
+            pre_mesh_negative_z_section_side(&mut self.negative_z, section, block_registry);
+        }
     }
 
     fn apply_greedy_meshing(&mut self) {
@@ -1,19 +1,29 @@
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk_section::NB_BLOCKS_PER_SECTION;
 use crate::game::world::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+use crate::game::world::light::chunk_light::{LightLevels, MAX_LIGHT_LEVEL};
 
-use super::blocks_resources::block_color;
-use super::chunk::NB_BLOCKS_PER_CHUNK;
+use super::ambient_occlusion::{should_flip_quad, FaceAmbientOcclusion};
+use super::blocks_resources::RenderLayer;
 use super::pre_mesh_creator::{BlockPreMesh, ChunkPreMesh, ChunkPreMeshOneDirection};
 use super::renderer::Face;
-use bevy::prelude::*;
+use super::texture_atlas::BlockTextureAtlas;
 
-use std::collections::HashMap;
-
-const DEFAULT_COLOR: Color = Color::FUCHSIA;
+// each light level lost make the face this much darker
+const LIGHT_FALLOFF: f32 = 0.8;
+// the darkest faces can still be seen a little
+const MIN_BRIGHTNESS: f32 = 0.05;
+// the brightness of a corner for each level of ambient occlusion, from the most occluded
+const AMBIENT_OCCLUSION_BRIGHTNESS: [f32; 4] = [0.5, 0.7, 0.85, 1.];
 
 pub struct FacesMesh {
     pub vertices: Vec<[f32; 3]>,
     pub vertices_normals: Vec<[f32; 3]>,
+    // the position on the face in blocks, the texture is repeated on each block
     pub vertices_uv: Vec<[f32; 2]>,
+    pub vertices_tile_origins: Vec<[f32; 2]>,
+    // the brightness given by the light in front of the face and the ambient occlusion
+    pub vertices_colors: Vec<[f32; 4]>,
     pub indices: Vec<u32>,
 }
 
@@ -23,6 +33,8 @@ impl FacesMesh {
             vertices: Vec::new(),
             vertices_normals: Vec::new(),
             vertices_uv: Vec::new(),
+            vertices_tile_origins: Vec::new(),
+            vertices_colors: Vec::new(),
             indices: Vec::new(),
         }
     }
@@ -32,6 +44,9 @@ impl FacesMesh {
         self.vertices_normals
             .append(&mut faces_mesh.vertices_normals);
         self.vertices_uv.append(&mut faces_mesh.vertices_uv);
+        self.vertices_tile_origins
+            .append(&mut faces_mesh.vertices_tile_origins);
+        self.vertices_colors.append(&mut faces_mesh.vertices_colors);
         self.add_indices(&mut faces_mesh.indices);
     }
 
@@ -46,112 +61,137 @@ impl FacesMesh {
     }
 }
 
-pub struct FacesMeshWithColor {
-    pub faces_mesh: FacesMesh,
-    pub color: Color,
+// The faces of the chunk in the mesh of their render layer, the translucent faces
+// are blended with what is behind them so they can't be drawn with the opaque ones.
+pub struct ChunkFacesMeshes {
+    pub opaque: FacesMesh,
+    pub translucent: FacesMesh,
 }
 
-impl FacesMeshWithColor {
-    pub fn new() -> Self {
-        Self {
-            faces_mesh: FacesMesh::new(),
-            color: DEFAULT_COLOR,
+impl ChunkFacesMeshes {
+    pub fn get_mut(&mut self, render_layer: RenderLayer) -> &mut FacesMesh {
+        match render_layer {
+            RenderLayer::Opaque => &mut self.opaque,
+            RenderLayer::Translucent => &mut self.translucent,
         }
     }
 }
 
-pub fn get_faces_mesh(chunk_pre_mesh: &ChunkPreMesh) -> HashMap<u32, FacesMeshWithColor> {
-    let mut faces = HashMap::new();
-
-    convert_chunk_pre_mesh_data_into_faces_mesh(
-        &mut faces,
-        &chunk_pre_mesh.positive_x,
-        Face::PositiveX,
-    );
-    convert_chunk_pre_mesh_data_into_faces_mesh(
-        &mut faces,
-        &chunk_pre_mesh.negative_x,
-        Face::NegativeX,
-    );
-    convert_chunk_pre_mesh_data_into_faces_mesh(
-        &mut faces,
-        &chunk_pre_mesh.positive_y,
-        Face::PositiveY,
-    );
-    convert_chunk_pre_mesh_data_into_faces_mesh(
-        &mut faces,
-        &chunk_pre_mesh.negative_y,
-        Face::NegativeY,
-    );
-    convert_chunk_pre_mesh_data_into_faces_mesh(
-        &mut faces,
-        &chunk_pre_mesh.positive_z,
-        Face::PositiveZ,
-    );
-    convert_chunk_pre_mesh_data_into_faces_mesh(
-        &mut faces,
-        &chunk_pre_mesh.negative_z,
-        Face::NegativeZ,
-    );
-
-    faces
+// all the faces of the chunk, their textures come from the atlas
+pub fn get_faces_meshes(
+    sections_pre_mesh: &[ChunkPreMesh],
+    texture_atlas: &BlockTextureAtlas,
+    block_registry: &BlockRegistry,
+) -> ChunkFacesMeshes {
+    let mut faces_meshes = ChunkFacesMeshes {
+        opaque: FacesMesh::new(),
+        translucent: FacesMesh::new(),
+    };
+
+    for section_pre_mesh in sections_pre_mesh {
+        let directions = [
+            (&section_pre_mesh.positive_x, Face::PositiveX),
+            (&section_pre_mesh.negative_x, Face::NegativeX),
+            (&section_pre_mesh.positive_y, Face::PositiveY),
+            (&section_pre_mesh.negative_y, Face::NegativeY),
+            (&section_pre_mesh.positive_z, Face::PositiveZ),
+            (&section_pre_mesh.negative_z, Face::NegativeZ),
+        ];
+
+        for (pre_mesh_in_a_direction, direction) in directions {
+            convert_chunk_pre_mesh_data_into_faces_mesh(
+                &mut faces_meshes,
+                pre_mesh_in_a_direction,
+                direction,
+                section_pre_mesh.section_index,
+                texture_atlas,
+                block_registry,
+            );
+        }
+    }
+
+    faces_meshes
 }
 
 fn convert_chunk_pre_mesh_data_into_faces_mesh(
-    faces: &mut HashMap<u32, FacesMeshWithColor>,
+    faces_meshes: &mut ChunkFacesMeshes,
     chunk_pre_mesh_in_a_direction: &ChunkPreMeshOneDirection,
     direction: Face,
+    section_index: usize,
+    texture_atlas: &BlockTextureAtlas,
+    block_registry: &BlockRegistry,
 ) {
-    for pre_mesh_inedex in 0..NB_BLOCKS_PER_CHUNK {
+    for pre_mesh_inedex in 0..NB_BLOCKS_PER_SECTION {
         let pre_mesh = chunk_pre_mesh_in_a_direction.get_pre_mesh(pre_mesh_inedex);
 
         if pre_mesh.get_stretch().get_as_u16() == 0 {
             continue;
         }
 
+        let block_coords =
+            BlockCoordsInChunk::from_section_block_index(section_index, pre_mesh_inedex);
+
         let mut mesh = match direction {
             Face::PositiveX => {
-                convert_block_pre_mesh_to_faces_mesh_on_positive_x(pre_mesh, pre_mesh_inedex)
+                convert_block_pre_mesh_to_faces_mesh_on_positive_x(pre_mesh, &block_coords)
             }
             Face::NegativeX => {
-                convert_block_pre_mesh_to_faces_mesh_on_negative_x(pre_mesh, pre_mesh_inedex)
+                convert_block_pre_mesh_to_faces_mesh_on_negative_x(pre_mesh, &block_coords)
             }
             Face::PositiveY => {
-                convert_block_pre_mesh_to_faces_mesh_on_positive_y(pre_mesh, pre_mesh_inedex)
+                convert_block_pre_mesh_to_faces_mesh_on_positive_y(pre_mesh, &block_coords)
             }
             Face::NegativeY => {
-                convert_block_pre_mesh_to_faces_mesh_on_negative_y(pre_mesh, pre_mesh_inedex)
+                convert_block_pre_mesh_to_faces_mesh_on_negative_y(pre_mesh, &block_coords)
             }
             Face::PositiveZ => {
-                convert_block_pre_mesh_to_faces_mesh_on_positive_z(pre_mesh, pre_mesh_inedex)
+                convert_block_pre_mesh_to_faces_mesh_on_positive_z(pre_mesh, &block_coords)
             }
             Face::NegativeZ => {
-                convert_block_pre_mesh_to_faces_mesh_on_negative_z(pre_mesh, pre_mesh_inedex)
+                convert_block_pre_mesh_to_faces_mesh_on_negative_z(pre_mesh, &block_coords)
             }
         };
 
-        let extracted_color = block_color(pre_mesh.get_block().get_id());
-        let color = extracted_color.unwrap_or(DEFAULT_COLOR);
-        let key = color.as_rgba_u32();
-
-        if !faces.contains_key(&key) {
-            let mut faces_mesh_with_texture = FacesMeshWithColor::new();
-            faces_mesh_with_texture.color = color;
-            faces_mesh_with_texture.faces_mesh = mesh;
-            faces.insert(key, faces_mesh_with_texture);
-        } else {
-            faces.get_mut(&key).unwrap().faces_mesh.add(&mut mesh);
+        let block_id = pre_mesh.get_block().get_id();
+        let block_face = chunk_pre_mesh_in_a_direction.get_block_face(pre_mesh_inedex);
+        let tile_origin = texture_atlas.get_tile_origin(block_id, block_face);
+        mesh.vertices_tile_origins = vec![tile_origin; 4];
+        let light = chunk_pre_mesh_in_a_direction.get_light(pre_mesh_inedex);
+        let ambient_occlusion =
+            chunk_pre_mesh_in_a_direction.get_ambient_occlusion(pre_mesh_inedex);
+        mesh.vertices_colors = get_vertices_colors(light, &ambient_occlusion);
+        if should_flip_quad(&ambient_occlusion) {
+            // the two triangles are split along the other diagonal
+            mesh.indices = mesh.indices.iter().map(|index| (index + 1) % 4).collect();
         }
+
+        let render_layer = block_registry.get_render_layer(block_id);
+        faces_meshes.get_mut(render_layer).add(&mut mesh);
     }
 }
 
+pub fn get_vertices_colors(
+    light: LightLevels,
+    ambient_occlusion: &FaceAmbientOcclusion,
+) -> Vec<[f32; 4]> {
+    let darkness = (MAX_LIGHT_LEVEL - light.get_max_level()) as i32;
+    let light_brightness = LIGHT_FALLOFF.powi(darkness).max(MIN_BRIGHTNESS);
+    ambient_occlusion
+        .iter()
+        .map(|corner_occlusion| {
+            let brightness =
+                light_brightness * AMBIENT_OCCLUSION_BRIGHTNESS[*corner_occlusion as usize];
+            [brightness, brightness, brightness, 1.]
+        })
+        .collect()
+}
+
 fn convert_block_pre_mesh_to_faces_mesh_on_positive_x(
     block_pre_mesh: BlockPreMesh,
-    block_index: usize,
+    block_coords: &BlockCoordsInChunk,
 ) -> FacesMesh {
     let (_, stretch_y, stretch_z) = block_pre_mesh.get_stretch().get_final_stretch();
-    let (block_x, block_y, block_z) =
-        BlockCoordsInChunk::from_block_index(block_index).pos_tuple_f32();
+    let (block_x, block_y, block_z) = block_coords.pos_tuple_f32();
     let px = block_x + 1.;
     let py = block_y + 1.;
     let pz = block_z + 1.;
@@ -164,7 +204,12 @@ fn convert_block_pre_mesh_to_faces_mesh_on_positive_x(
         [px, py, pz - stretch_z],
     ];
     face.vertices_normals = vec![[1., 0., 0.], [1., 0., 0.], [1., 0., 0.], [1., 0., 0.]];
-    face.vertices_uv = vec![[1., 1.], [1., 1.], [1., 1.], [1., 1.]];
+    face.vertices_uv = vec![
+        [0., 0.],
+        [0., stretch_y],
+        [stretch_z, stretch_y],
+        [stretch_z, 0.],
+    ];
     face.indices = vec![0, 1, 2, 0, 2, 3];
 
     face
@@ -172,11 +217,10 @@ fn convert_block_pre_mesh_to_faces_mesh_on_positive_x(
 
 fn convert_block_pre_mesh_to_faces_mesh_on_negative_x(
     block_pre_mesh: BlockPreMesh,
-    block_index: usize,
+    block_coords: &BlockCoordsInChunk,
 ) -> FacesMesh {
     let (_, stretch_y, stretch_z) = block_pre_mesh.get_stretch().get_final_stretch();
-    let (block_x, block_y, block_z) =
-        BlockCoordsInChunk::from_block_index(block_index).pos_tuple_f32();
+    let (block_x, block_y, block_z) = block_coords.pos_tuple_f32();
     let px = block_x;
     let py = block_y + 1.;
     let pz = block_z + 1.;
@@ -189,7 +233,12 @@ fn convert_block_pre_mesh_to_faces_mesh_on_negative_x(
         [px, py, pz - stretch_z],
     ];
     face.vertices_normals = vec![[-1., 0., 0.], [-1., 0., 0.], [-1., 0., 0.], [-1., 0., 0.]];
-    face.vertices_uv = vec![[1., 1.], [1., 1.], [1., 1.], [1., 1.]];
+    face.vertices_uv = vec![
+        [stretch_z, 0.],
+        [stretch_z, stretch_y],
+        [0., stretch_y],
+        [0., 0.],
+    ];
     face.indices = vec![0, 2, 1, 0, 3, 2];
 
     face
@@ -197,11 +246,10 @@ fn convert_block_pre_mesh_to_faces_mesh_on_negative_x(
 
 fn convert_block_pre_mesh_to_faces_mesh_on_positive_y(
     block_pre_mesh: BlockPreMesh,
-    block_index: usize,
+    block_coords: &BlockCoordsInChunk,
 ) -> FacesMesh {
     let (stretch_x, _, stretch_z) = block_pre_mesh.get_stretch().get_final_stretch();
-    let (block_x, block_y, block_z) =
-        BlockCoordsInChunk::from_block_index(block_index).pos_tuple_f32();
+    let (block_x, block_y, block_z) = block_coords.pos_tuple_f32();
     let px = block_x + 1.;
     let py = block_y + 1.;
     let pz = block_z + 1.;
@@ -214,7 +262,12 @@ fn convert_block_pre_mesh_to_faces_mesh_on_positive_y(
         [px, py, pz - stretch_z],
     ];
     face.vertices_normals = vec![[0., 1., 0.], [0., 1., 0.], [0., 1., 0.], [0., 1., 0.]];
-    face.vertices_uv = vec![[1., 1.], [1., 1.], [1., 1.], [1., 1.]];
+    face.vertices_uv = vec![
+        [stretch_x, stretch_z],
+        [0., stretch_z],
+        [0., 0.],
+        [stretch_x, 0.],
+    ];
     face.indices = vec![0, 2, 1, 0, 3, 2];
 
     face
@@ -222,11 +275,10 @@ fn convert_block_pre_mesh_to_faces_mesh_on_positive_y(
 
 fn convert_block_pre_mesh_to_faces_mesh_on_negative_y(
     block_pre_mesh: BlockPreMesh,
-    block_index: usize,
+    block_coords: &BlockCoordsInChunk,
 ) -> FacesMesh {
     let (stretch_x, _, stretch_z) = block_pre_mesh.get_stretch().get_final_stretch();
-    let (block_x, block_y, block_z) =
-        BlockCoordsInChunk::from_block_index(block_index).pos_tuple_f32();
+    let (block_x, block_y, block_z) = block_coords.pos_tuple_f32();
     let px = block_x + 1.;
     let py = block_y;
     let pz = block_z + 1.;
@@ -239,7 +291,12 @@ fn convert_block_pre_mesh_to_faces_mesh_on_negative_y(
         [px, py, pz - stretch_z],
     ];
     face.vertices_normals = vec![[0., -1., 0.], [0., -1., 0.], [0., -1., 0.], [0., -1., 0.]];
-    face.vertices_uv = vec![[1., 1.], [1., 1.], [1., 1.], [1., 1.]];
+    face.vertices_uv = vec![
+        [stretch_x, stretch_z],
+        [0., stretch_z],
+        [0., 0.],
+        [stretch_x, 0.],
+    ];
     face.indices = vec![0, 1, 2, 0, 2, 3];
 
     face
@@ -247,11 +304,10 @@ fn convert_block_pre_mesh_to_faces_mesh_on_negative_y(
 
 fn convert_block_pre_mesh_to_faces_mesh_on_positive_z(
     block_pre_mesh: BlockPreMesh,
-    block_index: usize,
+    block_coords: &BlockCoordsInChunk,
 ) -> FacesMesh {
     let (stretch_x, stretch_y, _) = block_pre_mesh.get_stretch().get_final_stretch();
-    let (block_x, block_y, block_z) =
-        BlockCoordsInChunk::from_block_index(block_index).pos_tuple_f32();
+    let (block_x, block_y, block_z) = block_coords.pos_tuple_f32();
     let px = block_x + 1.;
     let py = block_y + 1.;
     let pz = block_z + 1.;
@@ -264,7 +320,12 @@ fn convert_block_pre_mesh_to_faces_mesh_on_positive_z(
         [px - stretch_x, py, pz],
     ];
     face.vertices_normals = vec![[0., 0., 1.], [0., 0., 1.], [0., 0., 1.], [0., 0., 1.]];
-    face.vertices_uv = vec![[1., 1.], [1., 1.], [1., 1.], [1., 1.]];
+    face.vertices_uv = vec![
+        [stretch_x, 0.],
+        [stretch_x, stretch_y],
+        [0., stretch_y],
+        [0., 0.],
+    ];
     face.indices = vec![0, 2, 1, 0, 3, 2];
 
     face
@@ -272,11 +333,10 @@ fn convert_block_pre_mesh_to_faces_mesh_on_positive_z(
 
 fn convert_block_pre_mesh_to_faces_mesh_on_negative_z(
     block_pre_mesh: BlockPreMesh,
-    block_index: usize,
+    block_coords: &BlockCoordsInChunk,
 ) -> FacesMesh {
     let (stretch_x, stretch_y, _) = block_pre_mesh.get_stretch().get_final_stretch();
-    let (block_x, block_y, block_z) =
-        BlockCoordsInChunk::from_block_index(block_index).pos_tuple_f32();
+    let (block_x, block_y, block_z) = block_coords.pos_tuple_f32();
     let px = block_x + 1.;
     let py = block_y + 1.;
     let pz = block_z;
@@ -289,7 +349,12 @@ fn convert_block_pre_mesh_to_faces_mesh_on_negative_z(
         [px - stretch_x, py, pz],
     ];
     face.vertices_normals = vec![[0., 0., -1.], [0., 0., -1.], [0., 0., -1.], [0., 0., -1.]];
-    face.vertices_uv = vec![[1., 1.], [1., 1.], [1., 1.], [1., 1.]];
+    face.vertices_uv = vec![
+        [0., 0.],
+        [0., stretch_y],
+        [stretch_x, stretch_y],
+        [stretch_x, 0.],
+    ];
     face.indices = vec![0, 1, 2, 0, 2, 3];
 
     face
@@ -1,7 +1,10 @@
 use super::chunk::Chunk;
+use super::chunk_material::{ChunkMaterial, ChunkMaterials, ATTRIBUTE_TILE_ORIGIN};
+use super::fluid_mesh::add_fluids_faces;
 use super::pre_mesh_creator::{ChunkPreMesh, NeighborChunks};
-use super::pre_mesh_to_bundle_conveter::get_faces_mesh;
-use crate::game::world::chunk::ChunkData;
+use super::pre_mesh_to_bundle_conveter::{get_faces_meshes, FacesMesh};
+use super::texture_atlas::BlockTextureAtlas;
+use crate::game::world::block_registry::BlockRegistry;
 use crate::game::world::world::CHUNK_SIZE_I32;
 use crate::world::World;
 use bevy::prelude::*;
@@ -25,7 +28,7 @@ pub struct ChunkMeshTag;
 #[derive(Bundle, Default)]
 struct ChunkMeshBundle {
     tag: ChunkMeshTag,
-    pbr_bundle: PbrBundle,
+    material_mesh_bundle: MaterialMeshBundle<ChunkMaterial>,
 }
 
 fn remove_old_meshes(
@@ -40,42 +43,36 @@ fn remove_old_meshes(
     }
 }
 
-fn get_chunk_data(
+fn get_chunk<'a>(
     chunk_coords: (i32, i32, i32),
     world_chunks: &HashMap<(i32, i32, i32), Entity>,
-    chunks: &Query<(Entity, &Parent, &Chunk)>,
-) -> Option<ChunkData> {
-    if !world_chunks.contains_key(&chunk_coords) {
-        return None;
-    }
-
-    match world_chunks.get(&chunk_coords) {
-        None => None,
-        Some(chunk_entity) => {
-            let (_, _, chunk) = chunks.get(*chunk_entity).unwrap();
-            Some(chunk.blocks.clone())
-        }
-    }
+    chunks: &'a Query<(Entity, &Parent, &Chunk)>,
+) -> Option<&'a Chunk> {
+    let chunk_entity = world_chunks.get(&chunk_coords)?;
+    let (_, _, chunk) = chunks.get(*chunk_entity).ok()?;
+    Some(chunk)
 }
 
-fn get_neighbor_chunks(
+fn get_neighbor_chunks<'a>(
     chunk_coords: (i32, i32, i32),
     world_chunks: &HashMap<(i32, i32, i32), Entity>,
-    chunks: &Query<(Entity, &Parent, &Chunk)>,
-) -> NeighborChunks {
+    chunks: &'a Query<(Entity, &Parent, &Chunk)>,
+) -> NeighborChunks<'a> {
     let (x, y, z) = chunk_coords;
     NeighborChunks {
-        positive_x: get_chunk_data((x + CHUNK_SIZE_I32, y, z), world_chunks, chunks),
-        negative_x: get_chunk_data((x - CHUNK_SIZE_I32, y, z), world_chunks, chunks),
-        positive_z: get_chunk_data((x, y, z + CHUNK_SIZE_I32), world_chunks, chunks),
-        negative_z: get_chunk_data((x, y, z - CHUNK_SIZE_I32), world_chunks, chunks),
+        positive_x: get_chunk((x + CHUNK_SIZE_I32, y, z), world_chunks, chunks),
+        negative_x: get_chunk((x - CHUNK_SIZE_I32, y, z), world_chunks, chunks),
+        positive_z: get_chunk((x, y, z + CHUNK_SIZE_I32), world_chunks, chunks),
+        negative_z: get_chunk((x, y, z - CHUNK_SIZE_I32), world_chunks, chunks),
     }
 }
 
 pub fn generate_chunk_mesh_system(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    chunk_materials: Res<ChunkMaterials>,
+    block_registry: Res<BlockRegistry>,
+    texture_atlas: Res<BlockTextureAtlas>,
     mut worlds: Query<(Entity, &mut World)>,
     chunks: Query<(Entity, &Parent, &Chunk)>,
     mut chunks_meshes: Query<(&Parent, Entity), With<ChunkMeshTag>>,
@@ -88,48 +85,67 @@ pub fn generate_chunk_mesh_system(
             let (_, _, chunk) = chunks.get(chunk_to_render_entity).unwrap();
             let neighbor_chunks = get_neighbor_chunks(chunk_coords, &world_chunks, &chunks);
 
-            let mut chunk_pre_meshes = ChunkPreMesh::new(chunk.as_slice());
-            chunk_pre_meshes.optimise(&neighbor_chunks);
-            let mut chunk_faces = get_faces_mesh(&chunk_pre_meshes);
-
-            let mut bundles = Vec::new();
-            for (_, faces_mesh_with_color) in chunk_faces.drain() {
-                let indices = faces_mesh_with_color.faces_mesh.indices;
-                let positions = faces_mesh_with_color.faces_mesh.vertices;
-                let normals = faces_mesh_with_color.faces_mesh.vertices_normals;
-                let uvs = faces_mesh_with_color.faces_mesh.vertices_uv;
-
-                let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-                mesh.set_indices(Some(mesh::Indices::U32(indices)));
-                mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
-                mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-                mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-
-                let pbr_bundle = PbrBundle {
-                    mesh: meshes.add(mesh),
-                    material: materials.add(faces_mesh_with_color.color.into()),
+            let mut sections_pre_mesh = Vec::new();
+            for (section_index, section) in chunk.blocks.get_sections().iter().enumerate() {
+                if section.is_uniform_air() {
+                    continue;
+                }
+
+                let mut section_pre_mesh = ChunkPreMesh::new(section, section_index);
+                section_pre_mesh.optimise(chunk, &neighbor_chunks, &block_registry);
+                sections_pre_mesh.push(section_pre_mesh);
+            }
+            let mut chunk_faces_meshes =
+                get_faces_meshes(&sections_pre_mesh, &texture_atlas, &block_registry);
+            add_fluids_faces(
+                &mut chunk_faces_meshes,
+                chunk,
+                &neighbor_chunks,
+                &texture_atlas,
+                &block_registry,
+            );
+            world_struct.chunks_to_render.remove(&chunk_coords);
+
+            let layers = [
+                (chunk_faces_meshes.opaque, &chunk_materials.opaque),
+                (chunk_faces_meshes.translucent, &chunk_materials.translucent),
+            ];
+            for (faces_mesh, material) in layers {
+                if faces_mesh.indices.is_empty() {
+                    continue;
+                }
+
+                let material_mesh_bundle = MaterialMeshBundle {
+                    mesh: meshes.add(create_mesh(faces_mesh)),
+                    material: material.clone(),
                     ..default()
                 };
 
                 let final_bundle = ChunkMeshBundle {
-                    pbr_bundle,
+                    material_mesh_bundle,
                     ..Default::default()
                 };
 
-                bundles.push(final_bundle);
+                let mesh_entity = commands.spawn(final_bundle).id();
+                commands
+                    .entity(chunk_to_render_entity)
+                    .push_children(&[mesh_entity]);
             }
-
-            world_struct.chunks_to_render.remove(&chunk_coords);
-            commands.add(move |world: &mut bevy::ecs::prelude::World| {
-                let entities: Vec<_> = world.spawn_batch(bundles).collect();
-                world
-                    .entity_mut(chunk_to_render_entity)
-                    .push_children(&entities);
-            });
         }
     }
 }
 
+fn create_mesh(faces_mesh: FacesMesh) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(mesh::Indices::U32(faces_mesh.indices)));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, faces_mesh.vertices);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, faces_mesh.vertices_normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, faces_mesh.vertices_uv);
+    mesh.insert_attribute(ATTRIBUTE_TILE_ORIGIN, faces_mesh.vertices_tile_origins);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, faces_mesh.vertices_colors);
+    mesh
+}
+
 fn get_chunks_to_render(
     world: &World,
     world_chunks: &HashMap<(i32, i32, i32), Entity>,
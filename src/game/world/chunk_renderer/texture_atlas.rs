@@ -0,0 +1,230 @@
+use crate::game::world::block_registry::{BlockFace, BlockRegistry};
+use bevy::log::error;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::{CompressedImageFormats, ImageSampler, ImageType};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const BLOCKS_TEXTURES_DIRECTORY: &str = "assets/textures/blocks";
+pub const TILE_SIZE: u32 = 16;
+const TEXTURE_EXTENSION: &str = "png";
+const NB_BYTES_PER_PIXEL: usize = 4;
+
+// used for the faces without texture nor color, and for unknown blocks
+const MISSING_TILE_COLOR: Color = Color::FUCHSIA;
+const MISSING_TILE: usize = 0;
+
+const TOP_TILE: usize = 0;
+const SIDE_TILE: usize = 1;
+const BOTTOM_TILE: usize = 2;
+const FRONT_TILE: usize = 3;
+const NB_TILES_PER_BLOCK: usize = 4;
+
+#[derive(Hash, PartialEq, Eq)]
+enum TileKey {
+    Texture(String),
+    Color(u32),
+}
+
+// All the faces textures packed in a single image, built once when the world is
+// opened from the block registry. A face with a texture get the tile loaded from
+// BLOCKS_TEXTURES_DIRECTORY, the other faces get a tile filled with the face color
+// or the block color. Identical tiles are only stored once.
+#[derive(Resource)]
+pub struct BlockTextureAtlas {
+    // the top, side, bottom and front tiles of each block, indexed by block id
+    blocks_tiles: Vec<[usize; NB_TILES_PER_BLOCK]>,
+    nb_tiles_per_row: usize,
+    nb_rows: usize,
+}
+
+impl BlockTextureAtlas {
+    pub fn new(block_registry: &BlockRegistry, textures_directory: &Path) -> (Self, Image) {
+        let mut tiles = vec![get_color_tile(MISSING_TILE_COLOR)];
+        let mut tiles_indices = HashMap::new();
+        let mut blocks_tiles = Vec::with_capacity(block_registry.get_nb_ids());
+
+        for block_id in 0..block_registry.get_nb_ids() {
+            let mut block_tiles = [MISSING_TILE; NB_TILES_PER_BLOCK];
+            if let Some(definition) = block_registry.get_definition(block_id as u16) {
+                // a front without texture looks like the sides
+                let front_texture = definition
+                    .textures
+                    .front
+                    .as_ref()
+                    .or(definition.textures.side.as_ref());
+                let faces_textures = [
+                    (TOP_TILE, BlockFace::Top, definition.textures.top.as_ref()),
+                    (
+                        SIDE_TILE,
+                        BlockFace::Side,
+                        definition.textures.side.as_ref(),
+                    ),
+                    (
+                        BOTTOM_TILE,
+                        BlockFace::Bottom,
+                        definition.textures.bottom.as_ref(),
+                    ),
+                    (FRONT_TILE, BlockFace::Front, front_texture),
+                ];
+
+                for (tile, block_face, texture) in faces_textures {
+                    let texture_tile = texture.and_then(|texture_name| {
+                        add_texture_tile(
+                            texture_name,
+                            textures_directory,
+                            &mut tiles,
+                            &mut tiles_indices,
+                        )
+                    });
+                    let color_tile = || {
+                        let color = definition.get_face_color(block_face)?;
+                        Some(add_color_tile(color, &mut tiles, &mut tiles_indices))
+                    };
+                    block_tiles[tile] = texture_tile.or_else(color_tile).unwrap_or(MISSING_TILE);
+                }
+            }
+            blocks_tiles.push(block_tiles);
+        }
+
+        let nb_tiles_per_row = (tiles.len() as f32).sqrt().ceil() as usize;
+        let nb_rows = tiles.len().div_ceil(nb_tiles_per_row);
+        let atlas = Self {
+            blocks_tiles,
+            nb_tiles_per_row,
+            nb_rows,
+        };
+        let image = atlas.create_image(&tiles);
+        (atlas, image)
+    }
+
+    fn create_image(&self, tiles: &[Vec<u8>]) -> Image {
+        let tile_size = TILE_SIZE as usize;
+        let width = self.nb_tiles_per_row * tile_size;
+        let height = self.nb_rows * tile_size;
+        let mut data = vec![0; width * height * NB_BYTES_PER_PIXEL];
+
+        for (tile_index, tile) in tiles.iter().enumerate() {
+            let tile_x = (tile_index % self.nb_tiles_per_row) * tile_size;
+            let tile_y = (tile_index / self.nb_tiles_per_row) * tile_size;
+            for row in 0..tile_size {
+                let row_len = tile_size * NB_BYTES_PER_PIXEL;
+                let source_start = row * row_len;
+                let destination_start = ((tile_y + row) * width + tile_x) * NB_BYTES_PER_PIXEL;
+                data[destination_start..destination_start + row_len]
+                    .copy_from_slice(&tile[source_start..source_start + row_len]);
+            }
+        }
+
+        let size = Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        };
+        let mut image = Image::new(
+            size,
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        // the tiles are small pixel art, they must not be blurred together
+        image.sampler_descriptor = ImageSampler::nearest();
+        image
+    }
+
+    // the size of a tile in uv coordinates
+    pub fn get_tile_size(&self) -> Vec2 {
+        Vec2::new(1. / self.nb_tiles_per_row as f32, 1. / self.nb_rows as f32)
+    }
+
+    // the uv coordinates of the top left corner of the tile of this face of the block,
+    // the face is the one of the block before it is oriented (see BlockRegistry::get_block_face)
+    pub fn get_tile_origin(&self, block_id: u16, block_face: BlockFace) -> [f32; 2] {
+        let tile = match block_face {
+            BlockFace::Top => TOP_TILE,
+            BlockFace::Side => SIDE_TILE,
+            BlockFace::Bottom => BOTTOM_TILE,
+            BlockFace::Front => FRONT_TILE,
+        };
+        let tile_index = self
+            .blocks_tiles
+            .get(block_id as usize)
+            .map_or(MISSING_TILE, |block_tiles| block_tiles[tile]);
+
+        let tile_size = self.get_tile_size();
+        [
+            (tile_index % self.nb_tiles_per_row) as f32 * tile_size.x,
+            (tile_index / self.nb_tiles_per_row) as f32 * tile_size.y,
+        ]
+    }
+}
+
+fn add_texture_tile(
+    texture_name: &str,
+    textures_directory: &Path,
+    tiles: &mut Vec<Vec<u8>>,
+    tiles_indices: &mut HashMap<TileKey, usize>,
+) -> Option<usize> {
+    let key = TileKey::Texture(texture_name.to_string());
+    if let Some(tile_index) = tiles_indices.get(&key) {
+        return Some(*tile_index);
+    }
+
+    let path = textures_directory.join(format!("{}.{}", texture_name, TEXTURE_EXTENSION));
+    match load_texture_tile(&path) {
+        Ok(tile) => {
+            tiles.push(tile);
+            tiles_indices.insert(key, tiles.len() - 1);
+            Some(tiles.len() - 1)
+        }
+        Err(err) => {
+            error!("Unable to load the block texture {:?} : {}", path, err);
+            None
+        }
+    }
+}
+
+fn add_color_tile(
+    color: Color,
+    tiles: &mut Vec<Vec<u8>>,
+    tiles_indices: &mut HashMap<TileKey, usize>,
+) -> usize {
+    let key = TileKey::Color(color.as_rgba_u32());
+    *tiles_indices.entry(key).or_insert_with(|| {
+        tiles.push(get_color_tile(color));
+        tiles.len() - 1
+    })
+}
+
+fn get_color_tile(color: Color) -> Vec<u8> {
+    let pixel = color
+        .as_rgba_f32()
+        .map(|channel| (channel.clamp(0., 1.) * 255.).round() as u8);
+    pixel.repeat((TILE_SIZE * TILE_SIZE) as usize)
+}
+
+fn load_texture_tile(path: &Path) -> Result<Vec<u8>, String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    let image = Image::from_buffer(
+        &bytes,
+        ImageType::Extension(TEXTURE_EXTENSION),
+        CompressedImageFormats::NONE,
+        true,
+    )
+    .map_err(|err| err.to_string())?;
+
+    let image = image
+        .convert(TextureFormat::Rgba8UnormSrgb)
+        .ok_or("unsupported texture format")?;
+
+    let size = image.texture_descriptor.size;
+    if size.width != TILE_SIZE || size.height != TILE_SIZE {
+        return Err(format!(
+            "the texture is {}x{} pixels instead of {}x{}",
+            size.width, size.height, TILE_SIZE, TILE_SIZE
+        ));
+    }
+    Ok(image.data)
+}
@@ -0,0 +1,70 @@
+use super::block::Block;
+use super::chunk::{CHUNK_SIZE, SECTION_HEIGHT};
+use super::paletted_blocks::PalettedBlocks;
+
+pub const NB_BLOCKS_PER_SECTION: usize = CHUNK_SIZE * CHUNK_SIZE * SECTION_HEIGHT;
+
+// A 16 blocks high slice of a chunk, the blocks are indexed like in a chunk :
+// x * (CHUNK_SIZE * SECTION_HEIGHT) + y * CHUNK_SIZE + z
+// A section made of a single block (all air above the ground, all stone deep
+// below it...) only store that block.
+#[derive(Clone)]
+pub enum ChunkSection {
+    Uniform(Block),
+    Paletted(PalettedBlocks),
+}
+
+impl Default for ChunkSection {
+    fn default() -> Self {
+        Self::Uniform(Block::default())
+    }
+}
+
+impl ChunkSection {
+    pub fn get_block(&self, index: usize) -> Block {
+        match self {
+            Self::Uniform(block) => *block,
+            Self::Paletted(paletted_blocks) => paletted_blocks.get_block(index),
+        }
+    }
+
+    pub fn set_block(&mut self, index: usize, block: Block) {
+        match self {
+            Self::Uniform(uniform_block) => {
+                if *uniform_block == block {
+                    return;
+                }
+                let mut paletted_blocks =
+                    PalettedBlocks::new(NB_BLOCKS_PER_SECTION, *uniform_block);
+                paletted_blocks.set_block(index, block);
+                *self = Self::Paletted(paletted_blocks);
+            }
+            Self::Paletted(paletted_blocks) => paletted_blocks.set_block(index, block),
+        }
+    }
+
+    pub fn get_uniform_block(&self) -> Option<Block> {
+        match self {
+            Self::Uniform(block) => Some(*block),
+            Self::Paletted(_) => None,
+        }
+    }
+
+    pub fn is_uniform_air(&self) -> bool {
+        self.get_uniform_block() == Some(Block::default())
+    }
+
+    // go back to a uniform section if every block ended up being the same
+    pub fn optimize(&mut self) {
+        let Self::Paletted(paletted_blocks) = self else {
+            return;
+        };
+
+        let first_block = paletted_blocks.get_block(0);
+        let is_uniform =
+            (1..NB_BLOCKS_PER_SECTION).all(|index| paletted_blocks.get_block(index) == first_block);
+        if is_uniform {
+            *self = Self::Uniform(first_block);
+        }
+    }
+}
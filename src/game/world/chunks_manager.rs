@@ -1,4 +1,7 @@
+use super::block_registry::BlockRegistry;
 use super::chunk::Chunk;
+use super::light::light_propagation::light_chunk;
+use super::storage::chunk_storage::load_chunk;
 use super::world::get_neighbor_chunks;
 use super::world::World;
 use super::world::CHUNK_SIZE_I32;
@@ -6,12 +9,14 @@ use super::world::DIST_TO_LOAD_CHUNK;
 use super::world::DIST_TO_UNLOAD_CHUNK;
 use super::world::RENDER_RADIUS;
 use crate::game::camera::{FirstPersonCamera, Position};
+use bevy::app::AppExit;
 use bevy::prelude::*;
 use bevy::tasks::{AsyncComputeTaskPool, Task};
 use futures_lite::future;
 use std::collections::{HashMap, HashSet};
 
 const NB_CHUNKS_MAX_GENERATING_IN_THE_BACKGROUND: usize = 1;
+const NB_CHUNKS_MAX_LOADING_IN_THE_BACKGROUND: usize = 4;
 
 #[derive(Bundle)]
 struct LoadChunkBundle {
@@ -27,6 +32,7 @@ pub struct ComputeChunk {
 
 pub fn manage_chunks_system(
     mut commands: Commands,
+    block_registry: Res<BlockRegistry>,
     mut worlds: Query<(Entity, &mut World)>,
     chunks: Query<(Entity, &mut Chunk)>,
     cameras_pos: Query<&Position, With<FirstPersonCamera>>,
@@ -36,9 +42,21 @@ pub fn manage_chunks_system(
         unload_chunks(&mut world, &mut commands, &chunks, &chunks_to_unload);
         let chunks_to_load = get_chunks_to_load(&world, &cameras_pos);
         let mut rated_chunks = rate_loading_priority(&chunks_to_load, &cameras_pos);
-        load_chunks(&mut world, world_entity, &mut commands, &mut rated_chunks);
+        load_chunks(
+            &mut world,
+            world_entity,
+            &mut commands,
+            &block_registry,
+            &mut rated_chunks,
+        );
 
-        start_generating_chunks(&mut world, world_entity, &mut commands, &rated_chunks);
+        start_generating_chunks(
+            &mut world,
+            world_entity,
+            &mut commands,
+            &block_registry,
+            &rated_chunks,
+        );
     }
 }
 
@@ -46,7 +64,7 @@ fn get_chunks_to_unload(
     world: &World,
     cameras_pos: &Query<&Position, With<FirstPersonCamera>>,
 ) -> HashSet<(i32, i32, i32)> {
-    let mut chunks_to_unload = world.loaded_chunks.clone();
+    let mut chunks_to_unload: HashSet<_> = world.loaded_chunks.keys().copied().collect();
     for camera_pos in cameras_pos.iter() {
         chunks_to_unload.retain(|chunk| -> bool {
             let diff_x_squared = (camera_pos.x as i32 - chunk.0).pow(2) as f32;
@@ -67,6 +85,11 @@ fn unload_chunks(
     for (chunk_entity, chunk) in chunks.iter() {
         let chunk_coords = chunk.get_coords().to_tuple();
         if chunks_to_unload.contains(&chunk_coords) {
+            if chunk.is_dirty() {
+                if let Err(err) = world.chunk_storage.save_chunk(chunk) {
+                    error!("Unable to save the chunk {:?} : {}", chunk_coords, err);
+                }
+            }
             commands.entity(chunk_entity).despawn_recursive();
             world.loaded_chunks.remove(&chunk_coords);
             let chunks_to_rerender = get_neighbor_chunks(&chunk_coords);
@@ -103,10 +126,13 @@ fn get_chunks_to_load(
         }
     }
 
-    chunks_to_load.retain(|chunk_coords| -> bool { !world.loaded_chunks.contains(chunk_coords) });
+    chunks_to_load
+        .retain(|chunk_coords| -> bool { !world.loaded_chunks.contains_key(chunk_coords) });
     chunks_to_load
         .retain(|chunk_coords| -> bool { !world.chunks_in_generation.contains(chunk_coords) });
     chunks_to_load
+        .retain(|chunk_coords| -> bool { !world.chunks_in_loading.contains(chunk_coords) });
+    chunks_to_load
 }
 
 fn rate_loading_priority(
@@ -133,25 +159,78 @@ fn rate_loading_priority(
     rated_chunks
 }
 
+fn sort_chunks_by_priority(rated_chunks: &HashMap<(i32, i32, i32), f32>) -> Vec<(i32, i32, i32)> {
+    let mut chunks_prioritized: Vec<(i32, i32, i32)> = rated_chunks.keys().cloned().collect();
+    chunks_prioritized.sort_by(|coords1, coords2| {
+        let score1 = rated_chunks.get(coords1).unwrap();
+        let score2 = rated_chunks.get(coords2).unwrap();
+        score1.partial_cmp(score2).unwrap()
+    });
+    chunks_prioritized
+}
+
 fn load_chunks(
     world: &mut World,
     world_entity: Entity,
     commands: &mut Commands,
+    block_registry: &BlockRegistry,
     chunks_to_load: &mut HashMap<(i32, i32, i32), f32>,
 ) {
-    // let has_already_been_generated = false;
-    // for chunk in chunks_to_load.keys() {
-    //     // TODO chunk saving and loding
-    //     if has_already_been_generated {
-    //         chunks_to_load.remove(chunk);
-    //     }
-    // }
+    let mut saved_chunks = chunks_to_load.clone();
+    saved_chunks.retain(|chunk_coords, _| world.chunk_storage.has_chunk(chunk_coords));
+
+    // the chunks already saved must never be generated again, even if they can't be loaded yet
+    for chunk_coords in saved_chunks.keys() {
+        chunks_to_load.remove(chunk_coords);
+    }
+
+    let nb_chunks_in_loading = world.chunks_in_loading.len();
+    if nb_chunks_in_loading >= NB_CHUNKS_MAX_LOADING_IN_THE_BACKGROUND {
+        return;
+    }
+
+    let thread_pool = AsyncComputeTaskPool::get();
+
+    let nb_chunks_to_load = NB_CHUNKS_MAX_LOADING_IN_THE_BACKGROUND - nb_chunks_in_loading;
+    let chunks_prioritized = sort_chunks_by_priority(&saved_chunks);
+
+    for &chunk_coords in chunks_prioritized.iter().take(nb_chunks_to_load) {
+        let (x, y, z) = chunk_coords;
+        let region_files = world.chunk_storage.get_region_files();
+        let world_generator = world.world_generator.clone();
+        let block_registry = block_registry.clone();
+        let task = thread_pool.spawn(async move {
+            let mut chunk = match load_chunk(&region_files, chunk_coords) {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    // a corrupted chunk is generated again so the world doesn't end up with a hole
+                    warn!(
+                        "Unable to load the chunk {:?}, regenerating it : {}",
+                        chunk_coords, err
+                    );
+                    let mut chunk = Chunk::new(x, y, z);
+                    world_generator.generate_chunk(&mut chunk);
+                    chunk
+                }
+            };
+            light_chunk(&mut chunk, &block_registry);
+            chunk
+        });
+
+        world.chunks_in_loading.insert(chunk_coords);
+
+        commands.spawn(ComputeChunk {
+            task,
+            parent_world: world_entity,
+        });
+    }
 }
 
 fn start_generating_chunks(
     world: &mut World,
     world_entity: Entity,
     commands: &mut Commands,
+    block_registry: &BlockRegistry,
     rated_chunks_to_generate: &HashMap<(i32, i32, i32), f32>,
 ) {
     let nb_chunks_in_generation = world.chunks_in_generation.len();
@@ -164,22 +243,18 @@ fn start_generating_chunks(
     let nb_chunks_to_generate =
         NB_CHUNKS_MAX_GENERATING_IN_THE_BACKGROUND - nb_chunks_in_generation;
 
-    let mut chunks_prioritized: Vec<(i32, i32, i32)> =
-        rated_chunks_to_generate.keys().cloned().collect();
-    chunks_prioritized.sort_by(|coords1, coords2| {
-        let score1 = rated_chunks_to_generate.get(coords1).unwrap();
-        let score2 = rated_chunks_to_generate.get(coords2).unwrap();
-        score1.partial_cmp(score2).unwrap()
-    });
+    let chunks_prioritized = sort_chunks_by_priority(rated_chunks_to_generate);
 
     let mut chunks_to_generate = chunks_prioritized.iter().take(nb_chunks_to_generate);
 
     while let Some(&chunk_coords) = chunks_to_generate.next() {
         let (x, y, z) = chunk_coords;
         let world_generator = world.world_generator.clone();
+        let block_registry = block_registry.clone();
         let task = thread_pool.spawn(async move {
             let mut chunk = Chunk::new(x, y, z);
             world_generator.generate_chunk(&mut chunk);
+            light_chunk(&mut chunk, &block_registry);
             chunk
         });
 
@@ -236,12 +311,44 @@ fn load_generated_chunk(
             commands.entity(chunk_entity).insert(load_chunk_bundle);
             commands.entity(chunk_parent).add_child(chunk_entity);
             commands.entity(chunk_entity).remove::<ComputeChunk>();
-            world.loaded_chunks.insert(computed_chunk_coords);
+            world
+                .loaded_chunks
+                .insert(computed_chunk_coords, chunk_entity);
             let chunks_to_rerender = get_neighbor_chunks(&computed_chunk_coords);
             world.chunks_to_render.extend(chunks_to_rerender);
             world.chunks_to_render.insert(computed_chunk_coords);
+            // the light of the chunk only knows about its own blocks for now
+            world.chunks_to_spread_light.insert(computed_chunk_coords);
             world.chunks_in_generation.remove(&computed_chunk_coords);
+            world.chunks_in_loading.remove(&computed_chunk_coords);
         }
         Err(_) => commands.entity(chunk_entity).despawn_recursive(),
     }
 }
+
+// the chunks still loaded when the app is closed would be lost otherwise
+pub fn save_chunks_on_exit_system(
+    mut exit_events: EventReader<AppExit>,
+    mut worlds: Query<(Entity, &mut World)>,
+    chunks: Query<(&Parent, &Chunk)>,
+) {
+    if exit_events.is_empty() {
+        return;
+    }
+    exit_events.clear();
+
+    for (world_entity, mut world) in worlds.iter_mut() {
+        for (chunk_parent, chunk) in chunks.iter() {
+            if chunk_parent.get() != world_entity || !chunk.is_dirty() {
+                continue;
+            }
+
+            if let Err(err) = world.chunk_storage.save_chunk(chunk) {
+                let chunk_coords = chunk.get_coords().to_tuple();
+                error!("Unable to save the chunk {:?} : {}", chunk_coords, err);
+            }
+        }
+
+        world.chunk_storage.compact_fragmented_regions();
+    }
+}
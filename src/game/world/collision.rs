@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+
+// the player can walk up a block without jumping
+pub const MAX_STEP_HEIGHT: f32 = 1.;
+// keep the boxes from touching so they are not considered overlapping after a move
+const COLLISION_MARGIN: f32 = 0.001;
+
+// an axis aligned bounding box
+#[derive(Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    // a box standing on feet_position, centered on it horizontally
+    pub fn from_feet_position(feet_position: Vec3, width: f32, height: f32) -> Self {
+        let half_width = width / 2.;
+        Self {
+            min: feet_position - Vec3::new(half_width, 0., half_width),
+            max: feet_position + Vec3::new(half_width, height, half_width),
+        }
+    }
+
+    fn from_block_position(block_position: IVec3) -> Self {
+        let min = block_position.as_vec3();
+        Self {
+            min,
+            max: min + Vec3::ONE,
+        }
+    }
+
+    pub fn translated(&self, translation: Vec3) -> Self {
+        Self {
+            min: self.min + translation,
+            max: self.max + translation,
+        }
+    }
+
+    // the box covering this one at the start and at the end of the motion
+    fn swept(&self, motion: Vec3) -> Self {
+        let moved = self.translated(motion);
+        Self {
+            min: self.min.min(moved.min),
+            max: self.max.max(moved.max),
+        }
+    }
+
+    fn overlaps_on_axis(&self, other: &Self, axis: usize) -> bool {
+        self.min[axis] < other.max[axis] && self.max[axis] > other.min[axis]
+    }
+}
+
+pub struct CollisionResult {
+    // how far the box could move before hitting a solid block
+    pub motion: Vec3,
+    // the axes on which the motion was stopped by a block
+    pub collided: BVec3,
+}
+
+impl CollisionResult {
+    pub fn is_on_ground(&self, wanted_motion: Vec3) -> bool {
+        self.collided.y && wanted_motion.y < 0.
+    }
+}
+
+// Move the box axis by axis, vertically first, and stop it against the solid
+// blocks on its way, so it can't go through a block whatever the motion length.
+// When can_step_up is true and a block stop the horizontal motion, the box try
+// to climb it if it is no higher than MAX_STEP_HEIGHT.
+pub fn move_and_collide(
+    aabb: &Aabb,
+    motion: Vec3,
+    can_step_up: bool,
+    is_solid: impl Fn(IVec3) -> bool,
+) -> CollisionResult {
+    let step_height = if can_step_up { MAX_STEP_HEIGHT } else { 0. };
+    let mut area = aabb.swept(motion);
+    area.max.y += step_height;
+    let blocks = get_solid_blocks_around(&area, &is_solid);
+
+    let result = sweep(aabb, motion, &blocks);
+    let is_horizontally_blocked = result.collided.x || result.collided.z;
+    if !can_step_up || !is_horizontally_blocked {
+        return result;
+    }
+
+    // go up, forward then back down and keep it if it went further
+    let step_up = sweep(aabb, Vec3::Y * MAX_STEP_HEIGHT, &blocks).motion.y;
+    let raised_aabb = aabb.translated(Vec3::Y * step_up);
+    let horizontal_motion = Vec3::new(motion.x, 0., motion.z);
+    let forward = sweep(&raised_aabb, horizontal_motion, &blocks);
+    let moved_aabb = raised_aabb.translated(forward.motion);
+    let down = sweep(
+        &moved_aabb,
+        Vec3::new(0., motion.y.min(0.) - step_up, 0.),
+        &blocks,
+    );
+
+    let stepped_motion = forward.motion + Vec3::Y * (step_up + down.motion.y);
+    let horizontal_distance = |motion: Vec3| Vec2::new(motion.x, motion.z).length_squared();
+    if horizontal_distance(stepped_motion) <= horizontal_distance(result.motion) {
+        return result;
+    }
+
+    CollisionResult {
+        motion: stepped_motion,
+        collided: BVec3::new(forward.collided.x, down.collided.y, forward.collided.z),
+    }
+}
+
+fn sweep(aabb: &Aabb, motion: Vec3, blocks: &[Aabb]) -> CollisionResult {
+    let mut moved_aabb = *aabb;
+    let mut allowed_motion = Vec3::ZERO;
+    let mut collided = [false; 3];
+
+    for axis in [1, 0, 2] {
+        let axis_motion = clip_axis_motion(&moved_aabb, axis, motion[axis], blocks);
+        collided[axis] = axis_motion != motion[axis];
+        allowed_motion[axis] = axis_motion;
+
+        let mut translation = Vec3::ZERO;
+        translation[axis] = axis_motion;
+        moved_aabb = moved_aabb.translated(translation);
+    }
+
+    CollisionResult {
+        motion: allowed_motion,
+        collided: BVec3::new(collided[0], collided[1], collided[2]),
+    }
+}
+
+// the part of the motion the box can do along this axis before touching a block
+fn clip_axis_motion(aabb: &Aabb, axis: usize, motion: f32, blocks: &[Aabb]) -> f32 {
+    let other_axes = [(axis + 1) % 3, (axis + 2) % 3];
+    let mut clipped_motion = motion;
+
+    for block in blocks {
+        let is_in_the_way = other_axes
+            .iter()
+            .all(|other_axis| aabb.overlaps_on_axis(block, *other_axis));
+        if !is_in_the_way {
+            continue;
+        }
+
+        if clipped_motion > 0. && block.min[axis] >= aabb.max[axis] - COLLISION_MARGIN {
+            let distance = block.min[axis] - aabb.max[axis] - COLLISION_MARGIN;
+            clipped_motion = clipped_motion.min(distance.max(0.));
+        } else if clipped_motion < 0. && block.max[axis] <= aabb.min[axis] + COLLISION_MARGIN {
+            let distance = block.max[axis] - aabb.min[axis] + COLLISION_MARGIN;
+            clipped_motion = clipped_motion.max(distance.min(0.));
+        }
+    }
+
+    clipped_motion
+}
+
+fn get_solid_blocks_around(area: &Aabb, is_solid: &impl Fn(IVec3) -> bool) -> Vec<Aabb> {
+    let min = area.min.floor().as_ivec3();
+    let max = area.max.ceil().as_ivec3();
+
+    let mut blocks = Vec::new();
+    for x in min.x..max.x {
+        for y in min.y..max.y {
+            for z in min.z..max.z {
+                let block_position = IVec3::new(x, y, z);
+                if is_solid(block_position) {
+                    blocks.push(Aabb::from_block_position(block_position));
+                }
+            }
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYER_WIDTH: f32 = 0.6;
+    const PLAYER_HEIGHT: f32 = 1.8;
+    const TOLERANCE: f32 = 0.01;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!(
+            (value - expected).abs() < TOLERANCE,
+            "{} is not close to {}",
+            value,
+            expected
+        );
+    }
+
+    fn get_player_aabb(feet_position: Vec3) -> Aabb {
+        Aabb::from_feet_position(feet_position, PLAYER_WIDTH, PLAYER_HEIGHT)
+    }
+
+    // the ground is every block under y = 0
+    fn is_ground(block_position: IVec3) -> bool {
+        block_position.y < 0
+    }
+
+    #[test]
+    fn falling_box_lands_on_the_ground() {
+        let aabb = get_player_aabb(Vec3::new(0.5, 2., 0.5));
+        let motion = Vec3::new(0., -5., 0.);
+
+        let result = move_and_collide(&aabb, motion, false, is_ground);
+        assert_close(result.motion.y, -2.);
+        assert!(result.is_on_ground(motion));
+        assert!(!result.collided.x && !result.collided.z);
+    }
+
+    #[test]
+    fn box_stops_against_the_walls_on_each_axis() {
+        let aabb = Aabb {
+            min: Vec3::splat(-0.3),
+            max: Vec3::splat(0.3),
+        };
+
+        for axis in 0..3 {
+            for direction in [1., -1.] {
+                // a wall 3 blocks away from the center of the box
+                let wall = if direction > 0. { 3 } else { -4 };
+                let is_wall = |block_position: IVec3| block_position[axis] == wall;
+                let mut motion = Vec3::ZERO;
+                motion[axis] = 10. * direction;
+
+                let result = move_and_collide(&aabb, motion, false, is_wall);
+                assert_close(result.motion[axis], 2.7 * direction);
+                assert!(<[bool; 3]>::from(result.collided)[axis]);
+                assert_eq!(result.motion.length_squared(), result.motion[axis].powi(2));
+            }
+        }
+    }
+
+    #[test]
+    fn box_steps_up_a_block() {
+        // a step of MAX_STEP_HEIGHT starting at x = 2
+        let is_solid = |block_position: IVec3| {
+            is_ground(block_position) || (block_position.x >= 2 && block_position.y == 0)
+        };
+        let aabb = get_player_aabb(Vec3::new(0.5, 0., 0.5));
+        let motion = Vec3::new(2., -0.1, 0.);
+
+        let result = move_and_collide(&aabb, motion, true, is_solid);
+        assert_close(result.motion.x, 2.);
+        assert_close(result.motion.y, MAX_STEP_HEIGHT);
+        assert!(result.is_on_ground(motion));
+
+        // without stepping up the box is stopped by the step
+        let result = move_and_collide(&aabb, motion, false, is_solid);
+        assert_close(result.motion.x, 2. - 0.8);
+        assert_close(result.motion.y, 0.);
+    }
+
+    #[test]
+    fn box_does_not_climb_a_ledge_of_two_blocks() {
+        let is_solid = |block_position: IVec3| {
+            is_ground(block_position) || (block_position.x >= 2 && block_position.y <= 1)
+        };
+        let aabb = get_player_aabb(Vec3::new(0.5, 0., 0.5));
+        let motion = Vec3::new(2., -0.1, 0.);
+
+        let result = move_and_collide(&aabb, motion, true, is_solid);
+        assert_close(result.motion.x, 2. - 0.8);
+        assert_close(result.motion.y, 0.);
+        assert!(result.collided.x);
+    }
+
+    #[test]
+    fn large_motions_do_not_go_through_blocks() {
+        // a wall a single block thick and the ground far below
+        let is_solid = |block_position: IVec3| block_position.x == 5 || block_position.y < -500;
+        let aabb = get_player_aabb(Vec3::new(0.5, 0., 0.5));
+
+        let result = move_and_collide(&aabb, Vec3::new(100., 0., 0.), false, is_solid);
+        assert_close(result.motion.x, 5. - 0.8);
+
+        let motion = Vec3::new(0., -1000., 0.);
+        let result = move_and_collide(&aabb, motion, false, is_solid);
+        assert_close(result.motion.y, -500.);
+        assert!(result.is_on_ground(motion));
+    }
+}
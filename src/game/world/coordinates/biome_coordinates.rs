@@ -1,26 +1,41 @@
-use std::i32::MIN;
-
-use super::chunk_coordinates::ChunkCoordinates;
+use super::global_coordinates::GlobalCoordinates;
 use crate::game::world::world_generator::biomes::biome::BIOME_SIZE_IN_BLOCKS;
 
-#[derive(Hash)]
+// The global coordinates of the corner of a biome
+#[derive(Hash, Clone, Copy, PartialEq, Eq)]
 pub struct BiomeCoordinates {
     x: u32,
     z: u32,
 }
 
 impl BiomeCoordinates {
-    pub fn from_chunk_coords(chunk_coords: &ChunkCoordinates) -> Self {
-        let (chunk_x, _, chunk_z) = chunk_coords.to_tuple();
-
-        // offset coordinates so it become global coordinates
-        let global_x = ((chunk_x as i64) - (MIN as i64)) as u32;
-        let global_z = ((chunk_z as i64) - (MIN as i64)) as u32;
-
+    pub fn from_global_coordinates(coords: &GlobalCoordinates) -> Self {
         // only keep a multiple of the biome size as coordinates
         Self {
-            x: global_x - (global_x % BIOME_SIZE_IN_BLOCKS as u32),
-            z: global_z - (global_z % BIOME_SIZE_IN_BLOCKS as u32),
+            x: coords.get_x() - (coords.get_x() % BIOME_SIZE_IN_BLOCKS as u32),
+            z: coords.get_z() - (coords.get_z() % BIOME_SIZE_IN_BLOCKS as u32),
         }
     }
+
+    // the biome offset_x biomes along the x axis and offset_z biomes along the z axis
+    pub fn get_neighbor(&self, offset_x: i32, offset_z: i32) -> Self {
+        let biome_size = BIOME_SIZE_IN_BLOCKS as i32;
+        Self {
+            x: self.x.wrapping_add_signed(offset_x * biome_size),
+            z: self.z.wrapping_add_signed(offset_z * biome_size),
+        }
+    }
+
+    // the position of the block column relative to the corner of this biome,
+    // it is outside of [0; BIOME_SIZE_IN_BLOCKS[ when the column belong to another biome
+    pub fn get_pos_in_biome(&self, coords: &GlobalCoordinates) -> [f64; 2] {
+        [
+            coords.get_x() as f64 - self.x as f64,
+            coords.get_z() as f64 - self.z as f64,
+        ]
+    }
+
+    pub fn to_tuple(&self) -> (u32, u32) {
+        (self.x, self.z)
+    }
 }
@@ -1,7 +1,10 @@
-use crate::game::world::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::game::world::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, SECTION_HEIGHT};
+use crate::game::world::world::CHUNK_SIZE_I32;
+use bevy::prelude::IVec3;
 
 // The position is stored like this
 // XXXX YYYY YYYY ZZZZ
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub struct BlockCoordsInChunk(u16);
 
 impl BlockCoordsInChunk {
@@ -16,6 +19,11 @@ impl BlockCoordsInChunk {
         Self(data)
     }
 
+    // every u16 is a valid position in a chunk
+    pub fn from_u16(data: u16) -> Self {
+        Self(data)
+    }
+
     pub fn from_block_index(block_index: usize) -> Self {
         let x = block_index / (CHUNK_SIZE * CHUNK_HEIGHT);
         let index = block_index - x * (CHUNK_SIZE * CHUNK_HEIGHT);
@@ -24,6 +32,30 @@ impl BlockCoordsInChunk {
         Self::new(x, y, z)
     }
 
+    pub fn from_section_block_index(section_index: usize, block_index: usize) -> Self {
+        let x = block_index / (CHUNK_SIZE * SECTION_HEIGHT);
+        let y = (block_index / CHUNK_SIZE) % SECTION_HEIGHT;
+        let z = block_index % CHUNK_SIZE;
+        Self::new(x, section_index * SECTION_HEIGHT + y, z)
+    }
+
+    // None when the block is above or below the world
+    pub fn from_global_block_position(block_position: IVec3) -> Option<Self> {
+        if block_position.y < 0 || block_position.y >= CHUNK_HEIGHT as i32 {
+            return None;
+        }
+        Some(Self::new(
+            block_position.x.rem_euclid(CHUNK_SIZE_I32) as usize,
+            block_position.y as usize,
+            block_position.z.rem_euclid(CHUNK_SIZE_I32) as usize,
+        ))
+    }
+
+    pub fn get_block_index(&self) -> usize {
+        let (x, y, z) = self.pos_tuple();
+        x as usize * (CHUNK_SIZE * CHUNK_HEIGHT) + y as usize * CHUNK_SIZE + z as usize
+    }
+
     pub fn get_x(&self) -> u8 {
         (self.0 >> 12) as u8
     }
@@ -1,3 +1,4 @@
+use crate::game::world::world::CHUNK_SIZE_I32;
 use bevy::prelude::*;
 
 #[derive(Clone, Default)]
@@ -12,6 +13,15 @@ impl ChunkCoordinates {
         ChunkCoordinates { x, y, z }
     }
 
+    // the chunk holding the block, chunks take the whole height of the world
+    pub fn from_global_block_position(block_position: IVec3) -> Self {
+        Self::new(
+            block_position.x.div_euclid(CHUNK_SIZE_I32) * CHUNK_SIZE_I32,
+            0,
+            block_position.z.div_euclid(CHUNK_SIZE_I32) * CHUNK_SIZE_I32,
+        )
+    }
+
     pub fn to_float_tuple(&self) -> (f32, f32, f32) {
         (self.x as f32, self.y as f32, self.z as f32)
     }
@@ -0,0 +1,53 @@
+use super::chunk_coordinates::ChunkCoordinates;
+use super::region_coordinates::RegionCoordinates;
+use crate::game::world::chunk::CHUNK_SIZE;
+use crate::game::world::storage::region_file::REGION_SIZE;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ChunkCoordsInRegion {
+    x: u8,
+    z: u8,
+}
+
+impl ChunkCoordsInRegion {
+    pub fn new(x: usize, z: usize) -> Self {
+        assert!(x < REGION_SIZE);
+        assert!(z < REGION_SIZE);
+
+        Self {
+            x: x as u8,
+            z: z as u8,
+        }
+    }
+
+    pub fn from_chunk_coords(chunk_coords: &ChunkCoordinates) -> Self {
+        let (chunk_x, _, chunk_z) = chunk_coords.to_tuple();
+        let chunk_index_x = chunk_x.div_euclid(CHUNK_SIZE as i32);
+        let chunk_index_z = chunk_z.div_euclid(CHUNK_SIZE as i32);
+
+        Self::new(
+            chunk_index_x.rem_euclid(REGION_SIZE as i32) as usize,
+            chunk_index_z.rem_euclid(REGION_SIZE as i32) as usize,
+        )
+    }
+
+    pub fn from_index(index: usize) -> Self {
+        Self::new(index / REGION_SIZE, index % REGION_SIZE)
+    }
+
+    pub fn get_index(&self) -> usize {
+        self.x as usize * REGION_SIZE + self.z as usize
+    }
+
+    pub fn to_chunk_coords(self, region_coords: &RegionCoordinates) -> ChunkCoordinates {
+        let (region_x, region_z) = region_coords.to_tuple();
+        let region_size = REGION_SIZE as i32;
+        let chunk_size = CHUNK_SIZE as i32;
+
+        ChunkCoordinates::new(
+            (region_x * region_size + self.x as i32) * chunk_size,
+            0,
+            (region_z * region_size + self.z as i32) * chunk_size,
+        )
+    }
+}
@@ -1,5 +1,6 @@
 pub mod biome_coordinates;
-pub mod block_coords_in_biome;
 pub mod block_coords_in_chunk;
 pub mod chunk_coordinates;
+pub mod chunk_coords_in_region;
 pub mod global_coordinates;
+pub mod region_coordinates;
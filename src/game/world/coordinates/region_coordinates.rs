@@ -0,0 +1,31 @@
+use super::chunk_coordinates::ChunkCoordinates;
+use crate::game::world::chunk::CHUNK_SIZE;
+use crate::game::world::storage::region_file::REGION_SIZE;
+
+// The regions are laid out on the X and Z axis only since a chunk already
+// cover the whole height of the world
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct RegionCoordinates {
+    x: i32,
+    z: i32,
+}
+
+impl RegionCoordinates {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    pub fn from_chunk_coords(chunk_coords: &ChunkCoordinates) -> Self {
+        let (chunk_x, _, chunk_z) = chunk_coords.to_tuple();
+        let region_size_in_blocks = (REGION_SIZE * CHUNK_SIZE) as i32;
+
+        Self {
+            x: chunk_x.div_euclid(region_size_in_blocks),
+            z: chunk_z.div_euclid(region_size_in_blocks),
+        }
+    }
+
+    pub fn to_tuple(self) -> (i32, i32) {
+        (self.x, self.z)
+    }
+}
@@ -0,0 +1,224 @@
+use super::block::{Block, BlockOrientation, MAX_FLUID_LEVEL};
+use super::block_registry::BlockRegistry;
+use super::chunk::Chunk;
+use super::world::World;
+use super::world_edit::{get_block_at, set_block_at_without_light, update_light_around_blocks};
+use bevy::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+// the fluids move by one block every tick
+const FLUID_TICK_DURATION: f32 = 0.25;
+// the fluid blocks not updated during a tick are updated during the next ones
+const MAX_FLUID_UPDATES_PER_TICK: usize = 512;
+
+// a source stay forever, the fluid flowing from it goes up to MAX_FLUID_LEVEL
+const SOURCE_LEVEL: u8 = 0;
+// the level of a fluid falling from the block above it
+const FALLING_LEVEL: u8 = 1;
+
+const HORIZONTAL_DIRECTIONS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+// The blocks whose fluid may have to flow, they are updated one tick after being scheduled.
+// Every block changed in the world schedule itself and its neighbors.
+pub struct FluidSimulation {
+    tick_timer: Timer,
+    blocks_to_update: VecDeque<IVec3>,
+    scheduled_blocks: HashSet<IVec3>,
+}
+
+impl FluidSimulation {
+    pub fn new() -> Self {
+        Self {
+            tick_timer: Timer::from_seconds(FLUID_TICK_DURATION, TimerMode::Repeating),
+            blocks_to_update: VecDeque::new(),
+            scheduled_blocks: HashSet::new(),
+        }
+    }
+
+    pub fn schedule_update(&mut self, block_position: IVec3) {
+        if self.scheduled_blocks.insert(block_position) {
+            self.blocks_to_update.push_back(block_position);
+        }
+    }
+
+    // the block and the 6 blocks touching it
+    pub fn schedule_update_around(&mut self, block_position: IVec3) {
+        self.schedule_update(block_position);
+        for direction in HORIZONTAL_DIRECTIONS
+            .iter()
+            .chain(&[IVec3::Y, IVec3::NEG_Y])
+        {
+            self.schedule_update(block_position + *direction);
+        }
+    }
+
+    // the blocks scheduled before this tick, up to the budget of a tick
+    fn take_blocks_to_update(&mut self) -> Vec<IVec3> {
+        let nb_blocks = self.blocks_to_update.len().min(MAX_FLUID_UPDATES_PER_TICK);
+        let blocks: Vec<IVec3> = self.blocks_to_update.drain(..nb_blocks).collect();
+        for block_position in blocks.iter() {
+            self.scheduled_blocks.remove(block_position);
+        }
+        blocks
+    }
+}
+
+impl Default for FluidSimulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn update_fluids_system(
+    time: Res<Time>,
+    block_registry: Res<BlockRegistry>,
+    mut worlds: Query<&mut World>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    for mut world in worlds.iter_mut() {
+        if !world
+            .fluid_simulation
+            .tick_timer
+            .tick(time.delta())
+            .just_finished()
+        {
+            continue;
+        }
+
+        let blocks_to_update = world.fluid_simulation.take_blocks_to_update();
+        let mut changed_blocks = Vec::new();
+        for block_position in blocks_to_update {
+            update_fluid(
+                &mut world,
+                &mut chunks,
+                &block_registry,
+                block_position,
+                &mut changed_blocks,
+            );
+        }
+        // the light is updated once for all the blocks changed during the tick
+        update_light_around_blocks(&mut world, &mut chunks, &block_registry, &changed_blocks);
+    }
+}
+
+// A flowing fluid takes the level given by the fluid feeding it, or dries up
+// without it. Then the fluid falls into the air below it, or spreads on the
+// sides when it can't fall. The blocks changed are added to changed_blocks.
+fn update_fluid(
+    world: &mut World,
+    chunks: &mut Query<&mut Chunk>,
+    block_registry: &BlockRegistry,
+    block_position: IVec3,
+    changed_blocks: &mut Vec<IVec3>,
+) {
+    let Some(mut block) = get_block_at(world, chunks, block_position) else {
+        return;
+    };
+    if !block_registry.is_fluid(block.get_id()) {
+        return;
+    }
+
+    if block.get_fluid_level() != SOURCE_LEVEL {
+        match get_fed_level(world, chunks, block_registry, block_position, block) {
+            None => {
+                set_block(
+                    world,
+                    chunks,
+                    block_position,
+                    Block::default(),
+                    changed_blocks,
+                );
+                return;
+            }
+            Some(level) if level != block.get_fluid_level() => {
+                block.set_fluid_level(level);
+                set_block(world, chunks, block_position, block, changed_blocks);
+            }
+            Some(_) => (),
+        }
+    }
+
+    let below_position = block_position + IVec3::NEG_Y;
+    let below = get_block_at(world, chunks, below_position);
+    if can_flow_into(below, block_registry) {
+        let falling_fluid = get_fluid_block(block.get_id(), FALLING_LEVEL);
+        set_block(world, chunks, below_position, falling_fluid, changed_blocks);
+        return;
+    }
+    if !can_spread_on(below, block, block_registry) || block.get_fluid_level() >= MAX_FLUID_LEVEL {
+        return;
+    }
+
+    let flowing_fluid = get_fluid_block(block.get_id(), block.get_fluid_level() + 1);
+    for direction in HORIZONTAL_DIRECTIONS {
+        let side_position = block_position + direction;
+        if can_flow_into(get_block_at(world, chunks, side_position), block_registry) {
+            set_block(world, chunks, side_position, flowing_fluid, changed_blocks);
+        }
+    }
+}
+
+fn set_block(
+    world: &mut World,
+    chunks: &mut Query<&mut Chunk>,
+    block_position: IVec3,
+    block: Block,
+    changed_blocks: &mut Vec<IVec3>,
+) {
+    if set_block_at_without_light(world, chunks, block_position, block) {
+        changed_blocks.push(block_position);
+    }
+}
+
+// The level a flowing fluid should have from the fluids around it : full under
+// the same fluid, one level more than the lowest fluid spreading into it from
+// the sides. None when nothing feed it anymore.
+fn get_fed_level(
+    world: &World,
+    chunks: &Query<&mut Chunk>,
+    block_registry: &BlockRegistry,
+    block_position: IVec3,
+    block: Block,
+) -> Option<u8> {
+    let is_same_fluid = |neighbor: Block| neighbor.get_id() == block.get_id();
+
+    let above = get_block_at(world, chunks, block_position + IVec3::Y);
+    if above.is_some_and(is_same_fluid) {
+        return Some(FALLING_LEVEL);
+    }
+
+    HORIZONTAL_DIRECTIONS
+        .iter()
+        .filter_map(|direction| {
+            let side_position = block_position + *direction;
+            let side = get_block_at(world, chunks, side_position)?;
+            if !is_same_fluid(side) || side.get_fluid_level() >= MAX_FLUID_LEVEL {
+                return None;
+            }
+
+            let below_side = get_block_at(world, chunks, side_position + IVec3::NEG_Y);
+            can_spread_on(below_side, side, block_registry).then(|| side.get_fluid_level() + 1)
+        })
+        .min()
+}
+
+// the fluids replace the air, and any other block that is neither solid nor a fluid
+fn can_flow_into(block: Option<Block>, block_registry: &BlockRegistry) -> bool {
+    block.is_some_and(|block| {
+        !block_registry.is_solid(block.get_id()) && !block_registry.is_fluid(block.get_id())
+    })
+}
+
+// a fluid only spreads on the sides when it lays on something, a fluid falling
+// on the same fluid merges with it
+fn can_spread_on(below: Option<Block>, fluid: Block, block_registry: &BlockRegistry) -> bool {
+    below.is_some_and(|below| {
+        !can_flow_into(Some(below), block_registry) && below.get_id() != fluid.get_id()
+    })
+}
+
+fn get_fluid_block(fluid_id: u16, level: u8) -> Block {
+    let mut block = Block::new(fluid_id, BlockOrientation::default());
+    block.set_fluid_level(level);
+    block
+}
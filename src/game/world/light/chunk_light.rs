@@ -0,0 +1,109 @@
+use crate::game::world::chunk::{get_section_indices, NB_SECTIONS_PER_CHUNK};
+use crate::game::world::chunk_section::NB_BLOCKS_PER_SECTION;
+
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightChannel {
+    // the light coming from the sky, it goes down without fading
+    Sky,
+    // the light emitted by the blocks
+    Block,
+}
+
+// The light of a block is stored like this -> SSSS BBBB
+// 4 bits for the sky light marked as "S"
+// 4 bits for the block light marked as "B"
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct LightLevels(u8);
+
+impl LightLevels {
+    pub fn new(sky_light: u8, block_light: u8) -> Self {
+        Self((sky_light << 4) | (block_light & 0x0F))
+    }
+
+    pub fn get(&self, channel: LightChannel) -> u8 {
+        match channel {
+            LightChannel::Sky => self.0 >> 4,
+            LightChannel::Block => self.0 & 0x0F,
+        }
+    }
+
+    pub fn set(&mut self, channel: LightChannel, level: u8) {
+        match channel {
+            LightChannel::Sky => self.0 = (self.0 & 0x0F) | (level << 4),
+            LightChannel::Block => self.0 = (self.0 & 0xF0) | (level & 0x0F),
+        }
+    }
+
+    // the brightest of the two lights
+    pub fn get_max_level(&self) -> u8 {
+        self.get(LightChannel::Sky)
+            .max(self.get(LightChannel::Block))
+    }
+}
+
+// same as the blocks, a section with the same light everywhere only store it once
+#[derive(Clone)]
+enum SectionLight {
+    Uniform(LightLevels),
+    Values(Vec<LightLevels>),
+}
+
+impl SectionLight {
+    fn optimize(&mut self) {
+        let Self::Values(values) = self else {
+            return;
+        };
+
+        let first_value = values[0];
+        if values.iter().all(|value| *value == first_value) {
+            *self = Self::Uniform(first_value);
+        }
+    }
+}
+
+// The light of every block of a chunk, indexed like the blocks.
+// It is not saved, it is computed again every time the chunk is loaded.
+#[derive(Clone)]
+pub struct ChunkLight {
+    sections: Vec<SectionLight>,
+}
+
+impl Default for ChunkLight {
+    fn default() -> Self {
+        let sections = vec![SectionLight::Uniform(LightLevels::default()); NB_SECTIONS_PER_CHUNK];
+        Self { sections }
+    }
+}
+
+impl ChunkLight {
+    pub fn get(&self, index: usize) -> LightLevels {
+        let (section_index, index_in_section) = get_section_indices(index);
+        match &self.sections[section_index] {
+            SectionLight::Uniform(light) => *light,
+            SectionLight::Values(values) => values[index_in_section],
+        }
+    }
+
+    pub fn set(&mut self, index: usize, light: LightLevels) {
+        let (section_index, index_in_section) = get_section_indices(index);
+        let section = &mut self.sections[section_index];
+        if let SectionLight::Uniform(uniform_light) = section {
+            if *uniform_light == light {
+                return;
+            }
+            *section = SectionLight::Values(vec![*uniform_light; NB_BLOCKS_PER_SECTION]);
+        }
+
+        if let SectionLight::Values(values) = section {
+            values[index_in_section] = light;
+        }
+    }
+
+    pub fn optimize_sections(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.optimize();
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use super::chunk_light::{LightChannel, LightLevels, MAX_LIGHT_LEVEL};
+use crate::game::world::block::Block;
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk::{Chunk, CHUNK_HEIGHT};
+use crate::game::world::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+use crate::game::world::coordinates::chunk_coordinates::ChunkCoordinates;
+use crate::game::world::world::{World, CHUNK_SIZE_I32};
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::DerefMut;
+
+const LIGHT_CHANNELS: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+const DIRECTIONS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+const TOP_OF_THE_WORLD: i32 = CHUNK_HEIGHT as i32 - 1;
+
+// The chunks the light can spread into, with global block positions.
+// It work with &mut Chunk as well as with the Mut<Chunk> of a query.
+pub struct LightedChunks<C> {
+    chunks: HashMap<(i32, i32, i32), C>,
+    // the chunks where the light changed, they have to be rendered again
+    modified_chunks: HashSet<(i32, i32, i32)>,
+}
+
+impl<C: DerefMut<Target = Chunk>> LightedChunks<C> {
+    pub fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            modified_chunks: HashSet::new(),
+        }
+    }
+
+    pub fn insert(&mut self, chunk: C) {
+        self.chunks.insert(chunk.get_coords().to_tuple(), chunk);
+    }
+
+    pub fn contains(&self, chunk_coords: &(i32, i32, i32)) -> bool {
+        self.chunks.contains_key(chunk_coords)
+    }
+
+    pub fn get_modified_chunks(&self) -> &HashSet<(i32, i32, i32)> {
+        &self.modified_chunks
+    }
+
+    fn get_chunk_and_index(&self, block_position: IVec3) -> Option<(&C, usize)> {
+        let block_coords = BlockCoordsInChunk::from_global_block_position(block_position)?;
+        let chunk_coords = ChunkCoordinates::from_global_block_position(block_position);
+        let chunk = self.chunks.get(&chunk_coords.to_tuple())?;
+        Some((chunk, block_coords.get_block_index()))
+    }
+
+    fn get_block(&self, block_position: IVec3) -> Option<Block> {
+        let (chunk, index) = self.get_chunk_and_index(block_position)?;
+        Some(chunk.get_block(index))
+    }
+
+    fn get_light(&self, block_position: IVec3) -> Option<LightLevels> {
+        let (chunk, index) = self.get_chunk_and_index(block_position)?;
+        Some(chunk.light.get(index))
+    }
+
+    fn set_light(&mut self, block_position: IVec3, channel: LightChannel, level: u8) {
+        let Some(block_coords) = BlockCoordsInChunk::from_global_block_position(block_position)
+        else {
+            return;
+        };
+        let chunk_coords = ChunkCoordinates::from_global_block_position(block_position).to_tuple();
+        let Some(chunk) = self.chunks.get_mut(&chunk_coords) else {
+            return;
+        };
+
+        let index = block_coords.get_block_index();
+        let mut light = chunk.light.get(index);
+        light.set(channel, level);
+        chunk.light.set(index, light);
+        self.modified_chunks.insert(chunk_coords);
+    }
+
+    fn get_light_level(&self, block_position: IVec3, channel: LightChannel) -> u8 {
+        self.get_light(block_position)
+            .map_or(0, |light| light.get(channel))
+    }
+
+    // the light can only go through the transparent blocks
+    fn lets_light_through(&self, block_position: IVec3, block_registry: &BlockRegistry) -> bool {
+        self.get_block(block_position)
+            .is_some_and(|block| block_registry.is_transparent(block.get_id()))
+    }
+}
+
+// Compute the light of a chunk alone, without its neighbors.
+// The sky light enter by the top of the chunk, the block light by the blocks emitting it.
+pub fn light_chunk(chunk: &mut Chunk, block_registry: &BlockRegistry) {
+    chunk.light = Default::default();
+    let (chunk_x, _, chunk_z) = chunk.get_coords().to_tuple();
+    let mut lighted_chunks = LightedChunks::new();
+    lighted_chunks.insert(chunk);
+
+    let mut sky_light_queue = VecDeque::new();
+    let mut block_light_queue = VecDeque::new();
+    for x in chunk_x..chunk_x + CHUNK_SIZE_I32 {
+        for z in chunk_z..chunk_z + CHUNK_SIZE_I32 {
+            let top_block_position = IVec3::new(x, TOP_OF_THE_WORLD, z);
+            if light_from_the_sky(&mut lighted_chunks, top_block_position, block_registry) {
+                sky_light_queue.push_back(top_block_position);
+            }
+
+            for y in 0..CHUNK_HEIGHT as i32 {
+                let block_position = IVec3::new(x, y, z);
+                if emit_block_light(&mut lighted_chunks, block_position, block_registry) {
+                    block_light_queue.push_back(block_position);
+                }
+            }
+        }
+    }
+
+    spread_light(
+        &mut lighted_chunks,
+        LightChannel::Sky,
+        sky_light_queue,
+        block_registry,
+    );
+    spread_light(
+        &mut lighted_chunks,
+        LightChannel::Block,
+        block_light_queue,
+        block_registry,
+    );
+
+    let chunk = lighted_chunks.chunks.into_values().next().unwrap();
+    chunk.light.optimize_sections();
+}
+
+// The light of a chunk just loaded and the light of its loaded neighbors
+// spread into each other through their borders.
+pub fn spread_light_through_borders<C: DerefMut<Target = Chunk>>(
+    lighted_chunks: &mut LightedChunks<C>,
+    chunk_coords: (i32, i32, i32),
+    block_registry: &BlockRegistry,
+) {
+    let (chunk_x, _, chunk_z) = chunk_coords;
+    let last_x = chunk_x + CHUNK_SIZE_I32 - 1;
+    let last_z = chunk_z + CHUNK_SIZE_I32 - 1;
+
+    // the blocks on both sides of each border
+    let mut border_columns = Vec::new();
+    for i in 0..CHUNK_SIZE_I32 {
+        border_columns.push((chunk_x, chunk_z + i));
+        border_columns.push((chunk_x - 1, chunk_z + i));
+        border_columns.push((last_x, chunk_z + i));
+        border_columns.push((last_x + 1, chunk_z + i));
+        border_columns.push((chunk_x + i, chunk_z));
+        border_columns.push((chunk_x + i, chunk_z - 1));
+        border_columns.push((chunk_x + i, last_z));
+        border_columns.push((chunk_x + i, last_z + 1));
+    }
+
+    for channel in LIGHT_CHANNELS {
+        let mut queue = VecDeque::new();
+        for (x, z) in border_columns.iter() {
+            for y in 0..CHUNK_HEIGHT as i32 {
+                let block_position = IVec3::new(*x, y, *z);
+                if lighted_chunks.get_light_level(block_position, channel) > 1 {
+                    queue.push_back(block_position);
+                }
+            }
+        }
+        spread_light(lighted_chunks, channel, queue, block_registry);
+    }
+}
+
+// Update the light around a block that was just changed : the light it was
+// blocking can go through it, the light it was emitting or letting through is
+// removed and the light of the blocks around spread again.
+pub fn update_light_after_edit<C: DerefMut<Target = Chunk>>(
+    lighted_chunks: &mut LightedChunks<C>,
+    block_position: IVec3,
+    block_registry: &BlockRegistry,
+) {
+    for channel in LIGHT_CHANNELS {
+        let mut queue = remove_light(lighted_chunks, block_position, channel);
+
+        let is_lighted = match channel {
+            LightChannel::Sky => {
+                block_position.y == TOP_OF_THE_WORLD
+                    && light_from_the_sky(lighted_chunks, block_position, block_registry)
+            }
+            LightChannel::Block => emit_block_light(lighted_chunks, block_position, block_registry),
+        };
+        if is_lighted {
+            queue.push_back(block_position);
+        }
+
+        for direction in DIRECTIONS {
+            let neighbor_position = block_position + direction;
+            if lighted_chunks.get_light_level(neighbor_position, channel) > 0 {
+                queue.push_back(neighbor_position);
+            }
+        }
+        spread_light(lighted_chunks, channel, queue, block_registry);
+    }
+}
+
+// return true if the block at the top of the world let the sky light in
+fn light_from_the_sky<C: DerefMut<Target = Chunk>>(
+    lighted_chunks: &mut LightedChunks<C>,
+    block_position: IVec3,
+    block_registry: &BlockRegistry,
+) -> bool {
+    let Some(block) = lighted_chunks.get_block(block_position) else {
+        return false;
+    };
+    if !block_registry.is_transparent(block.get_id()) {
+        return false;
+    }
+
+    let level = get_sky_light_going_down(MAX_LIGHT_LEVEL, block, block_registry);
+    lighted_chunks.set_light(block_position, LightChannel::Sky, level);
+    true
+}
+
+// return true if the block emit light
+fn emit_block_light<C: DerefMut<Target = Chunk>>(
+    lighted_chunks: &mut LightedChunks<C>,
+    block_position: IVec3,
+    block_registry: &BlockRegistry,
+) -> bool {
+    let Some(block) = lighted_chunks.get_block(block_position) else {
+        return false;
+    };
+    let light_level = block_registry.get_light_level(block.get_id());
+    if light_level == 0 {
+        return false;
+    }
+
+    lighted_chunks.set_light(block_position, LightChannel::Block, light_level);
+    true
+}
+
+// the full sky light goes straight down through the air, it fades in the fluids
+fn get_sky_light_going_down(level: u8, block: Block, block_registry: &BlockRegistry) -> u8 {
+    if level == MAX_LIGHT_LEVEL && !block_registry.is_fluid(block.get_id()) {
+        MAX_LIGHT_LEVEL
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+// flood fill the light from the blocks in the queue, a block only get the light
+// of a neighbor if it is brighter than its own
+fn spread_light<C: DerefMut<Target = Chunk>>(
+    lighted_chunks: &mut LightedChunks<C>,
+    channel: LightChannel,
+    mut queue: VecDeque<IVec3>,
+    block_registry: &BlockRegistry,
+) {
+    while let Some(block_position) = queue.pop_front() {
+        let level = lighted_chunks.get_light_level(block_position, channel);
+        if level <= 1 {
+            continue;
+        }
+
+        for direction in DIRECTIONS {
+            let neighbor_position = block_position + direction;
+            if !lighted_chunks.lets_light_through(neighbor_position, block_registry) {
+                continue;
+            }
+
+            let neighbor_level = if channel == LightChannel::Sky && direction == IVec3::NEG_Y {
+                let neighbor_block = lighted_chunks.get_block(neighbor_position).unwrap();
+                get_sky_light_going_down(level, neighbor_block, block_registry)
+            } else {
+                level - 1
+            };
+
+            if neighbor_level > lighted_chunks.get_light_level(neighbor_position, channel) {
+                lighted_chunks.set_light(neighbor_position, channel, neighbor_level);
+                queue.push_back(neighbor_position);
+            }
+        }
+    }
+}
+
+// Darken the block and every block that was lit through it. Return the blocks
+// still lit by another way on the edge of the darkened area, their light has to
+// spread again.
+fn remove_light<C: DerefMut<Target = Chunk>>(
+    lighted_chunks: &mut LightedChunks<C>,
+    block_position: IVec3,
+    channel: LightChannel,
+) -> VecDeque<IVec3> {
+    let mut relight_queue = VecDeque::new();
+    let mut remove_queue = VecDeque::new();
+
+    let level = lighted_chunks.get_light_level(block_position, channel);
+    lighted_chunks.set_light(block_position, channel, 0);
+    remove_queue.push_back((block_position, level));
+
+    while let Some((block_position, level)) = remove_queue.pop_front() {
+        for direction in DIRECTIONS {
+            let neighbor_position = block_position + direction;
+            let neighbor_level = lighted_chunks.get_light_level(neighbor_position, channel);
+            if neighbor_level == 0 {
+                continue;
+            }
+
+            let is_sky_column = channel == LightChannel::Sky
+                && direction == IVec3::NEG_Y
+                && level == MAX_LIGHT_LEVEL
+                && neighbor_level == MAX_LIGHT_LEVEL;
+            if neighbor_level < level || is_sky_column {
+                lighted_chunks.set_light(neighbor_position, channel, 0);
+                remove_queue.push_back((neighbor_position, neighbor_level));
+            } else {
+                relight_queue.push_back(neighbor_position);
+            }
+        }
+    }
+
+    relight_queue
+}
+
+pub fn spread_light_between_chunks_system(
+    block_registry: Res<BlockRegistry>,
+    mut worlds: Query<(Entity, &mut World)>,
+    mut chunks: Query<(&Parent, &mut Chunk)>,
+) {
+    for (world_entity, mut world) in worlds.iter_mut() {
+        if world.chunks_to_spread_light.is_empty() {
+            continue;
+        }
+
+        let mut lighted_chunks = LightedChunks::new();
+        for (chunk_parent, chunk) in chunks.iter_mut() {
+            if chunk_parent.get() == world_entity {
+                lighted_chunks.insert(chunk);
+            }
+        }
+
+        // the chunks spawned this frame are not in the query yet, they are kept for the next one
+        let mut chunks_to_spread_light = std::mem::take(&mut world.chunks_to_spread_light);
+        chunks_to_spread_light.retain(|chunk_coords| {
+            if !lighted_chunks.contains(chunk_coords) {
+                return true;
+            }
+            spread_light_through_borders(&mut lighted_chunks, *chunk_coords, &block_registry);
+            false
+        });
+
+        world.chunks_to_spread_light = chunks_to_spread_light;
+        let modified_chunks = lighted_chunks.get_modified_chunks().clone();
+        world.chunks_to_render.extend(modified_chunks);
+    }
+}
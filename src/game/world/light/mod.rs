@@ -0,0 +1,2 @@
+pub mod chunk_light;
+pub mod light_propagation;
@@ -0,0 +1,23 @@
+use super::block::Block;
+use super::chunk::Chunk;
+use super::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+use super::world::World;
+use bevy::prelude::*;
+
+// Read access to the blocks of the chunks loaded in a world with global block
+// positions, the chunk of a block is found by its entity in World::loaded_chunks.
+// get_chunk gives the chunk of an entity, so it works with any query of the chunks.
+// None when the chunk of the block is not loaded, the blocks above and below the
+// world are air.
+pub fn get_loaded_block<'a>(
+    world: &World,
+    get_chunk: impl FnOnce(Entity) -> Option<&'a Chunk>,
+    block_position: IVec3,
+) -> Option<Block> {
+    let chunk_entity = world.get_chunk_entity(block_position)?;
+    let chunk = get_chunk(chunk_entity)?;
+    match BlockCoordsInChunk::from_global_block_position(block_position) {
+        Some(block_coords) => Some(chunk.get_block(block_coords.get_block_index())),
+        None => Some(Block::default()),
+    }
+}
@@ -1,7 +1,18 @@
 pub mod block;
+pub mod block_entity;
+pub mod block_registry;
 pub mod chunk;
 pub mod chunk_renderer;
+pub mod chunk_section;
 pub mod chunks_manager;
+pub mod collision;
 pub mod coordinates;
+pub mod fluid_simulation;
+pub mod light;
+pub mod loaded_chunks;
+pub mod paletted_blocks;
+pub mod raycast;
+pub mod storage;
 pub mod world;
+pub mod world_edit;
 pub mod world_generator;
@@ -0,0 +1,237 @@
+use super::block::Block;
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+// Store blocks as indices into a palette of the distinct blocks they contain.
+// Each index only take as many bits as needed to address the palette, so a
+// container holding 4 different blocks use 2 bits per block instead of 16.
+// The indices are packed in u64 words and never cross a word boundary.
+#[derive(Clone)]
+pub struct PalettedBlocks {
+    palette: Vec<Block>,
+    bits_per_index: usize,
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl PalettedBlocks {
+    pub fn new(len: usize, block: Block) -> Self {
+        // with a single block in the palette, no bit is needed to address it
+        Self {
+            palette: vec![block],
+            bits_per_index: 0,
+            words: Vec::new(),
+            len,
+        }
+    }
+
+    // used to rebuild a container from its saved parts, returns None if they don't match
+    pub fn from_raw_parts(
+        palette: Vec<Block>,
+        bits_per_index: usize,
+        words: Vec<u64>,
+        len: usize,
+    ) -> Option<Self> {
+        if palette.is_empty() || bits_per_index > BITS_PER_WORD {
+            return None;
+        }
+        if get_nb_bits_needed(palette.len()) > bits_per_index {
+            return None;
+        }
+        if words.len() != get_nb_words_needed(len, bits_per_index) {
+            return None;
+        }
+
+        let paletted_blocks = Self {
+            palette,
+            bits_per_index,
+            words,
+            len,
+        };
+
+        let palette_len = paletted_blocks.palette.len();
+        let all_indices_are_valid =
+            (0..len).all(|index| paletted_blocks.get_palette_index(index) < palette_len);
+        all_indices_are_valid.then_some(paletted_blocks)
+    }
+
+    pub fn get_palette(&self) -> &[Block] {
+        self.palette.as_slice()
+    }
+
+    pub fn get_bits_per_index(&self) -> usize {
+        self.bits_per_index
+    }
+
+    pub fn get_words(&self) -> &[u64] {
+        self.words.as_slice()
+    }
+
+    pub fn get_block(&self, index: usize) -> Block {
+        self.palette[self.get_palette_index(index)]
+    }
+
+    pub fn set_block(&mut self, index: usize, block: Block) {
+        assert!(index < self.len);
+
+        let palette_index = match self.palette.iter().position(|b| *b == block) {
+            Some(palette_index) => palette_index,
+            None => self.add_to_palette(block),
+        };
+        self.set_palette_index(index, palette_index);
+    }
+
+    fn add_to_palette(&mut self, block: Block) -> usize {
+        self.palette.push(block);
+        let bits_needed = get_nb_bits_needed(self.palette.len());
+        if bits_needed > self.bits_per_index {
+            self.resize_indices(bits_needed);
+        }
+        self.palette.len() - 1
+    }
+
+    // repack every index with the new number of bits
+    fn resize_indices(&mut self, new_bits_per_index: usize) {
+        let old_indices: Vec<usize> = (0..self.len)
+            .map(|index| self.get_palette_index(index))
+            .collect();
+
+        self.bits_per_index = new_bits_per_index;
+        self.words = vec![0; get_nb_words_needed(self.len, new_bits_per_index)];
+        for (index, palette_index) in old_indices.into_iter().enumerate() {
+            self.set_palette_index(index, palette_index);
+        }
+    }
+
+    fn get_palette_index(&self, index: usize) -> usize {
+        if self.bits_per_index == 0 {
+            return 0;
+        }
+
+        let indices_per_word = BITS_PER_WORD / self.bits_per_index;
+        let word = self.words[index / indices_per_word];
+        let offset = (index % indices_per_word) * self.bits_per_index;
+        ((word >> offset) & get_mask(self.bits_per_index)) as usize
+    }
+
+    fn set_palette_index(&mut self, index: usize, palette_index: usize) {
+        if self.bits_per_index == 0 {
+            return;
+        }
+
+        let indices_per_word = BITS_PER_WORD / self.bits_per_index;
+        let word = &mut self.words[index / indices_per_word];
+        let offset = (index % indices_per_word) * self.bits_per_index;
+        *word &= !(get_mask(self.bits_per_index) << offset);
+        *word |= (palette_index as u64) << offset;
+    }
+}
+
+fn get_mask(nb_bits: usize) -> u64 {
+    if nb_bits >= BITS_PER_WORD {
+        return u64::MAX;
+    }
+    (1 << nb_bits) - 1
+}
+
+fn get_nb_bits_needed(palette_len: usize) -> usize {
+    if palette_len <= 1 {
+        return 0;
+    }
+    (usize::BITS - (palette_len - 1).leading_zeros()) as usize
+}
+
+fn get_nb_words_needed(len: usize, bits_per_index: usize) -> usize {
+    if bits_per_index == 0 {
+        return 0;
+    }
+    let indices_per_word = BITS_PER_WORD / bits_per_index;
+    len.div_ceil(indices_per_word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEN: usize = 4096;
+
+    // a different block for each palette index
+    fn get_test_block(palette_index: usize) -> Block {
+        Block(palette_index as u16 * 32)
+    }
+
+    #[test]
+    fn a_single_block_takes_no_bits() {
+        let paletted_blocks = PalettedBlocks::new(LEN, get_test_block(0));
+        assert_eq!(paletted_blocks.get_bits_per_index(), 0);
+        assert!(paletted_blocks.get_words().is_empty());
+        assert!(paletted_blocks.get_block(LEN - 1) == get_test_block(0));
+    }
+
+    #[test]
+    fn the_bits_per_index_grow_with_the_palette() {
+        let mut paletted_blocks = PalettedBlocks::new(LEN, get_test_block(0));
+        let expected_bits_per_index = [(2, 1), (3, 2), (4, 2), (5, 3), (9, 4), (17, 5)];
+
+        let mut nb_blocks = 1;
+        for (palette_len, bits_per_index) in expected_bits_per_index {
+            while nb_blocks < palette_len {
+                // spread the blocks so every word holds some of them
+                paletted_blocks.set_block(nb_blocks * 97 % LEN, get_test_block(nb_blocks));
+                nb_blocks += 1;
+            }
+            assert_eq!(paletted_blocks.get_palette().len(), palette_len);
+            assert_eq!(paletted_blocks.get_bits_per_index(), bits_per_index);
+
+            // the blocks set before the indices were repacked are still there
+            for palette_index in 1..nb_blocks {
+                let block = paletted_blocks.get_block(palette_index * 97 % LEN);
+                assert!(block == get_test_block(palette_index));
+            }
+            assert!(paletted_blocks.get_block(1) == get_test_block(0));
+        }
+    }
+
+    #[test]
+    fn indices_never_cross_a_word() {
+        let mut paletted_blocks = PalettedBlocks::new(LEN, get_test_block(0));
+        for palette_index in 1..5 {
+            paletted_blocks.set_block(palette_index, get_test_block(palette_index));
+        }
+
+        // 3 bits per index, 21 of them fit in a word and 1 bit is left unused
+        assert_eq!(paletted_blocks.get_bits_per_index(), 3);
+        assert_eq!(paletted_blocks.get_words().len(), LEN.div_ceil(21));
+        for index in 0..LEN {
+            paletted_blocks.set_block(index, get_test_block(index % 5));
+        }
+        for index in 0..LEN {
+            assert!(paletted_blocks.get_block(index) == get_test_block(index % 5));
+        }
+    }
+
+    #[test]
+    fn raw_parts_are_checked() {
+        let mut paletted_blocks = PalettedBlocks::new(LEN, get_test_block(0));
+        for index in 0..LEN {
+            paletted_blocks.set_block(index, get_test_block(index % 3));
+        }
+        let palette = paletted_blocks.get_palette().to_vec();
+        let words = paletted_blocks.get_words().to_vec();
+
+        let rebuilt = PalettedBlocks::from_raw_parts(palette.clone(), 2, words.clone(), LEN);
+        let rebuilt = rebuilt.unwrap();
+        for index in 0..LEN {
+            assert!(rebuilt.get_block(index) == get_test_block(index % 3));
+        }
+
+        // too few bits for the palette, the wrong number of words, an index out of the palette
+        assert!(PalettedBlocks::from_raw_parts(palette.clone(), 1, words.clone(), LEN).is_none());
+        assert!(
+            PalettedBlocks::from_raw_parts(palette.clone(), 2, words[1..].to_vec(), LEN).is_none()
+        );
+        let mut corrupted_words = words;
+        corrupted_words[0] |= 0x3;
+        assert!(PalettedBlocks::from_raw_parts(palette, 2, corrupted_words, LEN).is_none());
+    }
+}
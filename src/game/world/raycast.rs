@@ -0,0 +1,120 @@
+use super::block::Block;
+use super::block_registry::BlockRegistry;
+use super::chunk::Chunk;
+use super::chunk_renderer::blocks_resources::blocks_ids::AIR_BLOCK;
+use super::loaded_chunks::get_loaded_block;
+use super::world::World;
+use crate::game::camera::{get_camera_ray, FirstPersonCamera, Position, Rotation};
+use bevy::prelude::*;
+
+// how far the camera can reach a block
+pub const MAX_REACH_DISTANCE: f32 = 8.;
+
+#[derive(Clone, Copy)]
+pub struct RaycastHit {
+    pub block: Block,
+    pub block_position: IVec3,
+    // the normal of the face the ray entered the block by,
+    // zero when the ray start inside the block
+    pub normal: IVec3,
+    // from the origin of the ray to the face of the block
+    pub distance: f32,
+}
+
+// the block the camera is looking at, updated every frame
+#[derive(Component, Default)]
+pub struct TargetedBlock(pub Option<RaycastHit>);
+
+// Walk the blocks crossed by the ray one by one (voxel DDA) and return the first
+// one that is not air. The ray stop at max_distance or on a block get_block can't
+// give, like a block in a chunk that is not loaded.
+pub fn raycast(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    get_block: impl Fn(IVec3) -> Option<Block>,
+) -> Option<RaycastHit> {
+    let direction = direction.try_normalize()?;
+    let mut block_position = origin.floor().as_ivec3();
+
+    let mut step = IVec3::ZERO;
+    // the distance along the ray to the next block border on each axis
+    let mut next_border_distance = Vec3::splat(f32::INFINITY);
+    // the distance along the ray between two block borders on each axis
+    let mut border_to_border_distance = Vec3::splat(f32::INFINITY);
+    for axis in 0..3 {
+        let block_start = block_position[axis] as f32;
+        if direction[axis] > 0. {
+            step[axis] = 1;
+            next_border_distance[axis] = (block_start + 1. - origin[axis]) / direction[axis];
+        } else if direction[axis] < 0. {
+            step[axis] = -1;
+            next_border_distance[axis] = (origin[axis] - block_start) / -direction[axis];
+        }
+        if direction[axis] != 0. {
+            border_to_border_distance[axis] = 1. / direction[axis].abs();
+        }
+    }
+
+    let mut normal = IVec3::ZERO;
+    let mut distance = 0.;
+    loop {
+        let block = get_block(block_position)?;
+        if block.get_id() != AIR_BLOCK {
+            return Some(RaycastHit {
+                block,
+                block_position,
+                normal,
+                distance,
+            });
+        }
+
+        let axis = get_nearest_border_axis(next_border_distance);
+        distance = next_border_distance[axis];
+        if distance > max_distance {
+            return None;
+        }
+
+        block_position[axis] += step[axis];
+        next_border_distance[axis] += border_to_border_distance[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+}
+
+fn get_nearest_border_axis(next_border_distance: Vec3) -> usize {
+    if next_border_distance.x < next_border_distance.y {
+        if next_border_distance.x < next_border_distance.z {
+            0
+        } else {
+            2
+        }
+    } else if next_border_distance.y < next_border_distance.z {
+        1
+    } else {
+        2
+    }
+}
+
+// the fluids can't be targeted, the camera look through them
+pub fn update_targeted_block_system(
+    block_registry: Res<BlockRegistry>,
+    worlds: Query<&World>,
+    chunks: Query<&Chunk>,
+    mut cameras: Query<(&Position, &Rotation, &mut TargetedBlock), With<FirstPersonCamera>>,
+) {
+    let Ok(world) = worlds.get_single() else {
+        return;
+    };
+
+    for (position, rotation, mut targeted_block) in cameras.iter_mut() {
+        let (origin, direction) = get_camera_ray(position, rotation);
+        targeted_block.0 = raycast(origin, direction, MAX_REACH_DISTANCE, |block_position| {
+            let block = get_loaded_block(world, |entity| chunks.get(entity).ok(), block_position)?;
+            if block_registry.is_fluid(block.get_id()) {
+                return Some(Block::default());
+            }
+            Some(block)
+        });
+    }
+}
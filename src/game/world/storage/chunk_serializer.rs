@@ -0,0 +1,374 @@
+use crate::game::world::block::Block;
+use crate::game::world::block_entity::{BlockEntity, BlockEntityValue, ChunkBlockEntities};
+use crate::game::world::chunk::{ChunkData, NB_SECTIONS_PER_CHUNK};
+use crate::game::world::chunk_section::{ChunkSection, NB_BLOCKS_PER_SECTION};
+use crate::game::world::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+use crate::game::world::paletted_blocks::PalettedBlocks;
+use std::io::{Error, ErrorKind, Result};
+
+// A serialized chunk start with this header :
+// MMMM VV
+// 4 bytes of magic number marked as "M"
+// 2 bytes for the format version marked as "V"
+//
+// It is followed by the NB_SECTIONS_PER_CHUNK sections of the chunk,
+// each one starting with 1 byte telling how it is stored :
+// 0 -> a uniform section, followed by its block on 2 bytes
+// 1 -> a palette compressed section
+//
+// Then come the block entities of the chunk :
+// EEEE CC NN ...
+// 4 bytes for the number of block entities marked as "E"
+// then for each block entity, 2 bytes for its BlockCoordsInChunk marked as "C",
+// 2 bytes for its number of values marked as "N" and its values, each one stored like this :
+// LL SS... K DD...
+// 2 bytes for the length of the name marked as "L" followed by the name in utf8 marked as "S"
+// 1 byte for the kind of the value marked as "K" followed by the value marked as "D" :
+// 0 -> an integer on 8 bytes
+// 1 -> a float on 8 bytes
+// 2 -> a text, 4 bytes for its length followed by the text in utf8
+// 3 -> bytes, 4 bytes for their number followed by the bytes
+//
+// Palette compressed blocks are stored like this :
+// PP BB BB ... N WW WWWWWWWW WWWWWWWW ...
+// 2 bytes for the number of blocks in the palette marked as "P"
+// the blocks of the palette, 2 bytes each, marked as "B"
+// 1 byte for the number of bits per index marked as "N"
+// 2 bytes for the number of words followed by the words holding the packed indices, 8 bytes each, marked as "W"
+//
+// Everything is stored in little endian
+const CHUNK_MAGIC: &[u8; 4] = b"VICK";
+const FORMAT_VERSION: u16 = 1;
+
+const UNIFORM_SECTION: u8 = 0;
+const PALETTED_SECTION: u8 = 1;
+
+const INTEGER_VALUE: u8 = 0;
+const FLOAT_VALUE: u8 = 1;
+const TEXT_VALUE: u8 = 2;
+const BYTES_VALUE: u8 = 3;
+
+pub fn serialize_chunk_data(
+    chunk_data: &ChunkData,
+    block_entities: &ChunkBlockEntities,
+) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(CHUNK_MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+
+    for section in chunk_data.get_sections() {
+        match section {
+            ChunkSection::Uniform(block) => {
+                bytes.push(UNIFORM_SECTION);
+                bytes.extend_from_slice(&block.get_as_u16().to_le_bytes());
+            }
+            ChunkSection::Paletted(paletted_blocks) => {
+                bytes.push(PALETTED_SECTION);
+                serialize_paletted_blocks(paletted_blocks, &mut bytes);
+            }
+        }
+    }
+
+    serialize_block_entities(block_entities, &mut bytes);
+    bytes
+}
+
+fn serialize_paletted_blocks(paletted_blocks: &PalettedBlocks, bytes: &mut Vec<u8>) {
+    let palette = paletted_blocks.get_palette();
+    bytes.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+    for block in palette {
+        bytes.extend_from_slice(&block.get_as_u16().to_le_bytes());
+    }
+
+    let words = paletted_blocks.get_words();
+    bytes.push(paletted_blocks.get_bits_per_index() as u8);
+    bytes.extend_from_slice(&(words.len() as u16).to_le_bytes());
+    for word in words {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+}
+
+fn serialize_block_entities(block_entities: &ChunkBlockEntities, bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&(block_entities.len() as u32).to_le_bytes());
+    for (block_coords, block_entity) in block_entities.iter() {
+        bytes.extend_from_slice(&block_coords.get_as_u16().to_le_bytes());
+        bytes.extend_from_slice(&(block_entity.len() as u16).to_le_bytes());
+
+        for (name, value) in block_entity.iter() {
+            bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(name.as_bytes());
+            match value {
+                BlockEntityValue::Integer(integer) => {
+                    bytes.push(INTEGER_VALUE);
+                    bytes.extend_from_slice(&integer.to_le_bytes());
+                }
+                BlockEntityValue::Float(float) => {
+                    bytes.push(FLOAT_VALUE);
+                    bytes.extend_from_slice(&float.to_le_bytes());
+                }
+                BlockEntityValue::Text(text) => {
+                    bytes.push(TEXT_VALUE);
+                    bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(text.as_bytes());
+                }
+                BlockEntityValue::Bytes(value_bytes) => {
+                    bytes.push(BYTES_VALUE);
+                    bytes.extend_from_slice(&(value_bytes.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(value_bytes);
+                }
+            }
+        }
+    }
+}
+
+pub fn deserialize_chunk_data(bytes: &[u8]) -> Result<(ChunkData, ChunkBlockEntities)> {
+    let mut reader = ByteReader::new(bytes);
+    if reader.read_bytes(CHUNK_MAGIC.len())? != CHUNK_MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "not a chunk file"));
+    }
+
+    let version = reader.read_u16()?;
+    if version != FORMAT_VERSION {
+        let message = format!("unsupported chunk format version {}", version);
+        return Err(Error::new(ErrorKind::InvalidData, message));
+    }
+
+    let chunk_data = deserialize_sections(&mut reader)?;
+    let block_entities = deserialize_block_entities(&mut reader)?;
+
+    if !reader.is_at_end() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "trailing bytes after the chunk",
+        ));
+    }
+    Ok((chunk_data, block_entities))
+}
+
+fn deserialize_sections(reader: &mut ByteReader) -> Result<ChunkData> {
+    let mut sections = Vec::with_capacity(NB_SECTIONS_PER_CHUNK);
+    for _ in 0..NB_SECTIONS_PER_CHUNK {
+        let section = match reader.read_u8()? {
+            UNIFORM_SECTION => ChunkSection::Uniform(Block(reader.read_u16()?)),
+            PALETTED_SECTION => {
+                let paletted_blocks = deserialize_paletted_blocks(reader, NB_BLOCKS_PER_SECTION)?;
+                ChunkSection::Paletted(paletted_blocks)
+            }
+            _ => return Err(Error::new(ErrorKind::InvalidData, "unknown section kind")),
+        };
+        sections.push(section);
+    }
+    Ok(ChunkData::from_sections(sections))
+}
+
+fn deserialize_block_entities(reader: &mut ByteReader) -> Result<ChunkBlockEntities> {
+    let mut block_entities = ChunkBlockEntities::default();
+    let nb_block_entities = reader.read_u32()?;
+    for _ in 0..nb_block_entities {
+        let block_coords = BlockCoordsInChunk::from_u16(reader.read_u16()?);
+        let nb_values = reader.read_u16()?;
+        let mut block_entity = BlockEntity::new();
+
+        for _ in 0..nb_values {
+            let name_len = reader.read_u16()? as usize;
+            let name = read_utf8(reader, name_len)?;
+            let value = match reader.read_u8()? {
+                INTEGER_VALUE => BlockEntityValue::Integer(i64::from_le_bytes(
+                    reader.read_bytes(8)?.try_into().unwrap(),
+                )),
+                FLOAT_VALUE => BlockEntityValue::Float(f64::from_le_bytes(
+                    reader.read_bytes(8)?.try_into().unwrap(),
+                )),
+                TEXT_VALUE => {
+                    let text_len = reader.read_u32()? as usize;
+                    BlockEntityValue::Text(read_utf8(reader, text_len)?)
+                }
+                BYTES_VALUE => {
+                    let nb_bytes = reader.read_u32()? as usize;
+                    BlockEntityValue::Bytes(reader.read_bytes(nb_bytes)?.to_vec())
+                }
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "unknown block entity value kind",
+                    ))
+                }
+            };
+            block_entity
+                .set(&name, value)
+                .map_err(|err| Error::new(ErrorKind::InvalidData, format!("{:?}", err)))?;
+        }
+        block_entities.set(block_coords, block_entity);
+    }
+    Ok(block_entities)
+}
+
+fn read_utf8(reader: &mut ByteReader, nb_bytes: usize) -> Result<String> {
+    let bytes = reader.read_bytes(nb_bytes)?;
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid utf8 in a block entity"))
+}
+
+fn deserialize_paletted_blocks(reader: &mut ByteReader, len: usize) -> Result<PalettedBlocks> {
+    let palette_len = reader.read_u16()? as usize;
+    let mut palette = Vec::with_capacity(palette_len);
+    for _ in 0..palette_len {
+        palette.push(Block(reader.read_u16()?));
+    }
+
+    let bits_per_index = reader.read_u8()? as usize;
+    let nb_words = reader.read_u16()? as usize;
+
+    let mut words = Vec::with_capacity(nb_words);
+    for _ in 0..nb_words {
+        words.push(reader.read_u64()?);
+    }
+
+    PalettedBlocks::from_raw_parts(palette, bits_per_index, words, len)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "corrupted chunk palette"))
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn read_bytes(&mut self, nb_bytes: usize) -> Result<&'a [u8]> {
+        let end = self.position + nb_bytes;
+        let Some(bytes) = self.bytes.get(self.position..end) else {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated chunk data"));
+        };
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        let bytes = self.read_bytes(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn get_nb_bytes_left(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.get_nb_bytes_left() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::world::block::BlockOrientation;
+    use crate::game::world::block_entity::{BlockEntityError, MAX_VALUE_NAME_LEN};
+    use crate::game::world::chunk::Chunk;
+
+    fn save_and_load(chunk: &Chunk) -> Chunk {
+        let bytes = serialize_chunk_data(&chunk.blocks, &chunk.block_entities);
+        let mut loaded_chunk = Chunk::new(0, 0, 0);
+        (loaded_chunk.blocks, loaded_chunk.block_entities) =
+            deserialize_chunk_data(&bytes).unwrap();
+        loaded_chunk
+    }
+
+    #[test]
+    fn block_states_and_block_entities_are_saved() {
+        let mut chunk = Chunk::new(0, 0, 0);
+        let block_coords = BlockCoordsInChunk::new(3, 70, 12);
+        let block_index = block_coords.get_block_index();
+
+        let mut block = Block::new(5, BlockOrientation::NegativeZ);
+        block.set_state(2);
+        chunk.set_block(block_index, block);
+
+        let mut block_entity = BlockEntity::new();
+        block_entity
+            .set("count", BlockEntityValue::Integer(-42))
+            .unwrap();
+        block_entity
+            .set("speed", BlockEntityValue::Float(0.5))
+            .unwrap();
+        block_entity
+            .set("label", BlockEntityValue::Text("chest".to_string()))
+            .unwrap();
+        block_entity
+            .set("content", BlockEntityValue::Bytes(vec![1, 2, 3]))
+            .unwrap();
+        chunk.set_block_entity(block_coords, block_entity.clone());
+
+        let loaded_chunk = save_and_load(&chunk);
+        let loaded_block = loaded_chunk.get_block(block_index);
+        assert_eq!(loaded_block.get_id(), 5);
+        assert_eq!(loaded_block.get_state(), 2);
+        assert_eq!(loaded_block.get_orientation(), BlockOrientation::NegativeZ);
+        assert_eq!(
+            loaded_chunk.get_block_entity(&block_coords),
+            Some(&block_entity)
+        );
+        assert_eq!(loaded_chunk.block_entities.len(), 1);
+    }
+
+    #[test]
+    fn removed_block_entities_are_not_saved() {
+        let mut chunk = Chunk::new(0, 0, 0);
+        let block_coords = BlockCoordsInChunk::new(0, 0, 0);
+        let mut block_entity = BlockEntity::new();
+        block_entity
+            .set("count", BlockEntityValue::Integer(1))
+            .unwrap();
+        chunk.set_block_entity(block_coords, block_entity);
+
+        if let Some(block_entity) = chunk.get_block_entity_mut(&block_coords) {
+            block_entity.remove("count");
+        }
+        assert!(chunk.get_block_entity(&block_coords).unwrap().is_empty());
+        chunk.remove_block_entity(&block_coords);
+
+        let loaded_chunk = save_and_load(&chunk);
+        assert!(loaded_chunk.block_entities.is_empty());
+    }
+
+    #[test]
+    fn values_too_long_to_be_saved_are_refused() {
+        let mut block_entity = BlockEntity::new();
+        let long_name = "a".repeat(MAX_VALUE_NAME_LEN + 1);
+        assert_eq!(
+            block_entity.set(&long_name, BlockEntityValue::Integer(0)),
+            Err(BlockEntityError::NameTooLong)
+        );
+        assert!(block_entity.is_empty());
+    }
+
+    #[test]
+    fn corrupted_chunks_are_refused() {
+        let chunk = Chunk::new(0, 0, 0);
+        let bytes = serialize_chunk_data(&chunk.blocks, &chunk.block_entities);
+
+        assert!(deserialize_chunk_data(&bytes[..bytes.len() - 1]).is_err());
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert!(deserialize_chunk_data(&wrong_magic).is_err());
+        let mut trailing_bytes = bytes;
+        trailing_bytes.push(0);
+        assert!(deserialize_chunk_data(&trailing_bytes).is_err());
+    }
+}
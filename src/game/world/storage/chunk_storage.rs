@@ -0,0 +1,190 @@
+use super::chunk_serializer::{deserialize_chunk_data, serialize_chunk_data};
+use super::region_file::RegionFile;
+use crate::game::world::chunk::Chunk;
+use crate::game::world::coordinates::chunk_coordinates::ChunkCoordinates;
+use crate::game::world::coordinates::chunk_coords_in_region::ChunkCoordsInRegion;
+use crate::game::world::coordinates::region_coordinates::RegionCoordinates;
+use bevy::log::error;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub const SAVES_DIRECTORY: &str = "saves";
+const REGIONS_DIRECTORY: &str = "regions";
+const REGION_FILE_EXTENSION: &str = "region";
+
+// past this ratio of free sectors a region file is compacted when the world is saved
+const MAX_FREE_SECTORS_RATIO: f32 = 0.25;
+
+// The region files opened so far, shared between the main thread and the loading tasks
+#[derive(Clone)]
+pub struct RegionFiles {
+    regions_directory: PathBuf,
+    opened_regions: Arc<Mutex<HashMap<RegionCoordinates, RegionFile>>>,
+}
+
+impl RegionFiles {
+    fn new(regions_directory: PathBuf) -> Self {
+        Self {
+            regions_directory,
+            opened_regions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn get_region_path(&self, region_coords: &RegionCoordinates) -> PathBuf {
+        let (x, z) = region_coords.to_tuple();
+        let file_name = format!("r.{}.{}.{}", x, z, REGION_FILE_EXTENSION);
+        self.regions_directory.join(file_name)
+    }
+
+    fn with_region<T>(
+        &self,
+        region_coords: RegionCoordinates,
+        action: impl FnOnce(&mut RegionFile) -> Result<T>,
+    ) -> Result<T> {
+        let mut opened_regions = self.opened_regions.lock().unwrap();
+        if !opened_regions.contains_key(&region_coords) {
+            let region_file = RegionFile::open(&self.get_region_path(&region_coords))?;
+            opened_regions.insert(region_coords, region_file);
+        }
+        action(opened_regions.get_mut(&region_coords).unwrap())
+    }
+
+    pub fn read_chunk(&self, chunk_coords: &ChunkCoordinates) -> Result<Option<Vec<u8>>> {
+        let region_coords = RegionCoordinates::from_chunk_coords(chunk_coords);
+        let chunk_pos = ChunkCoordsInRegion::from_chunk_coords(chunk_coords);
+        self.with_region(region_coords, |region| region.read_chunk(&chunk_pos))
+    }
+
+    pub fn write_chunk(&self, chunk_coords: &ChunkCoordinates, data: &[u8]) -> Result<()> {
+        let region_coords = RegionCoordinates::from_chunk_coords(chunk_coords);
+        let chunk_pos = ChunkCoordsInRegion::from_chunk_coords(chunk_coords);
+        self.with_region(region_coords, |region| region.write_chunk(&chunk_pos, data))
+    }
+
+    fn compact_fragmented_regions(&self) {
+        let mut opened_regions = self.opened_regions.lock().unwrap();
+        for (region_coords, region) in opened_regions.iter_mut() {
+            let free_sectors_ratio =
+                region.get_nb_free_sectors() as f32 / region.get_nb_sectors() as f32;
+            if free_sectors_ratio <= MAX_FREE_SECTORS_RATIO {
+                continue;
+            }
+
+            if let Err(err) = region.compact() {
+                error!("Unable to compact the region {:?} : {}", region_coords, err);
+            }
+        }
+    }
+}
+
+// Keep track of the chunks saved on the disk so we don't have to hit the
+// file system every frame to know if a chunk has already been generated
+pub struct ChunkStorage {
+    region_files: RegionFiles,
+    saved_chunks: HashSet<(i32, i32, i32)>,
+}
+
+// everything saved about a world is in this directory
+pub fn get_world_directory(world_name: &str) -> PathBuf {
+    Path::new(SAVES_DIRECTORY).join(world_name)
+}
+
+impl ChunkStorage {
+    pub fn new(world_name: &str) -> Self {
+        let regions_directory = get_world_directory(world_name).join(REGIONS_DIRECTORY);
+
+        if let Err(err) = fs::create_dir_all(&regions_directory) {
+            error!(
+                "Unable to create the save directory {:?} : {}",
+                regions_directory, err
+            );
+        }
+
+        let saved_chunks = get_saved_chunks(&regions_directory);
+
+        Self {
+            region_files: RegionFiles::new(regions_directory),
+            saved_chunks,
+        }
+    }
+
+    pub fn has_chunk(&self, chunk_coords: &(i32, i32, i32)) -> bool {
+        self.saved_chunks.contains(chunk_coords)
+    }
+
+    pub fn get_region_files(&self) -> RegionFiles {
+        self.region_files.clone()
+    }
+
+    pub fn save_chunk(&mut self, chunk: &Chunk) -> Result<()> {
+        let bytes = serialize_chunk_data(&chunk.blocks, &chunk.block_entities);
+        self.region_files.write_chunk(&chunk.get_coords(), &bytes)?;
+        self.saved_chunks.insert(chunk.get_coords().to_tuple());
+        Ok(())
+    }
+
+    pub fn compact_fragmented_regions(&self) {
+        self.region_files.compact_fragmented_regions();
+    }
+}
+
+// This function only need the region files so it can be called from an async task
+pub fn load_chunk(region_files: &RegionFiles, chunk_coords: (i32, i32, i32)) -> Result<Chunk> {
+    let (x, y, z) = chunk_coords;
+    let mut chunk = Chunk::new(x, y, z);
+
+    let Some(bytes) = region_files.read_chunk(&chunk.get_coords())? else {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "the chunk is not in its region file",
+        ));
+    };
+
+    (chunk.blocks, chunk.block_entities) = deserialize_chunk_data(&bytes)?;
+    chunk.mark_as_saved();
+    Ok(chunk)
+}
+
+fn get_saved_chunks(regions_directory: &Path) -> HashSet<(i32, i32, i32)> {
+    let mut saved_chunks = HashSet::new();
+    let Ok(entries) = fs::read_dir(regions_directory) else {
+        return saved_chunks;
+    };
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(region_coords) = parse_region_file_name(&file_name.to_string_lossy()) else {
+            continue;
+        };
+
+        match RegionFile::open(&entry.path()) {
+            Ok(region) => {
+                for chunk_pos in region.get_saved_chunks() {
+                    let chunk_coords = chunk_pos.to_chunk_coords(&region_coords);
+                    saved_chunks.insert(chunk_coords.to_tuple());
+                }
+            }
+            Err(err) => error!(
+                "Unable to read the region file {:?} : {}",
+                entry.path(),
+                err
+            ),
+        }
+    }
+    saved_chunks
+}
+
+// region files are named like this -> r.X.Z.region
+fn parse_region_file_name(file_name: &str) -> Option<RegionCoordinates> {
+    let parts: Vec<&str> = file_name.split('.').collect();
+    if parts.len() != 4 || parts[0] != "r" || parts[3] != REGION_FILE_EXTENSION {
+        return None;
+    }
+
+    let x = parts[1].parse().ok()?;
+    let z = parts[2].parse().ok()?;
+    Some(RegionCoordinates::new(x, z))
+}
@@ -0,0 +1,3 @@
+pub mod chunk_serializer;
+pub mod chunk_storage;
+pub mod region_file;
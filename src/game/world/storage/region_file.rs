@@ -0,0 +1,533 @@
+use crate::game::world::coordinates::chunk_coords_in_region::ChunkCoordsInRegion;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Result, Seek, SeekFrom, Write};
+use std::path::Path;
+
+pub const REGION_SIZE: usize = 32;
+pub const NB_CHUNKS_PER_REGION: usize = REGION_SIZE * REGION_SIZE;
+pub const SECTOR_SIZE: usize = 4096;
+
+// The header is made of one location per chunk of the region, stored like this :
+// SSSS LLLL
+// 4 bytes for the index of the first sector of the chunk marked as "S" (0 if the chunk is absent)
+// 4 bytes for the length in bytes of the chunk data marked as "L"
+// Both are stored in little endian
+const LOCATION_SIZE: usize = 8;
+const HEADER_SIZE: usize = NB_CHUNKS_PER_REGION * LOCATION_SIZE;
+const NB_HEADER_SECTORS: usize = HEADER_SIZE / SECTOR_SIZE;
+
+#[derive(Clone, Copy, Default)]
+struct ChunkLocation {
+    first_sector: u32,
+    length: u32,
+}
+
+impl ChunkLocation {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            first_sector: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            length: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; LOCATION_SIZE] {
+        let mut bytes = [0; LOCATION_SIZE];
+        bytes[0..4].copy_from_slice(&self.first_sector.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.length.to_le_bytes());
+        bytes
+    }
+
+    fn is_empty(&self) -> bool {
+        self.first_sector == 0
+    }
+
+    fn get_first_sector(&self) -> usize {
+        self.first_sector as usize
+    }
+
+    fn get_nb_sectors(&self) -> usize {
+        get_nb_sectors_for(self.length as usize)
+    }
+}
+
+fn get_nb_sectors_for(length: usize) -> usize {
+    length.div_ceil(SECTOR_SIZE)
+}
+
+// A region file pack REGION_SIZE * REGION_SIZE chunks in a single file.
+// The file is cut into sectors of SECTOR_SIZE bytes, the first ones hold the
+// header and every chunk occupy a contiguous run of sectors after it.
+// It only deal with raw bytes so it can be used without the rest of the game.
+pub struct RegionFile {
+    file: File,
+    locations: Vec<ChunkLocation>,
+    used_sectors: Vec<bool>,
+}
+
+impl RegionFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let file_length = file.metadata()?.len() as usize;
+        if file_length < HEADER_SIZE {
+            // a new (or truncated) region file, nothing in it can be trusted
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&[0; HEADER_SIZE])?;
+        }
+
+        let mut header = vec![0; HEADER_SIZE];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+
+        let nb_sectors = get_nb_sectors_for(file_length.max(HEADER_SIZE));
+        let mut region_file = Self {
+            file,
+            locations: vec![ChunkLocation::default(); NB_CHUNKS_PER_REGION],
+            used_sectors: vec![false; nb_sectors],
+        };
+        region_file.mark_sectors_as_used(0, NB_HEADER_SECTORS);
+
+        for (index, location_bytes) in header.chunks_exact(LOCATION_SIZE).enumerate() {
+            let location = ChunkLocation::from_bytes(location_bytes);
+            if location.is_empty() || !region_file.location_is_valid(&location) {
+                continue;
+            }
+
+            region_file
+                .mark_sectors_as_used(location.get_first_sector(), location.get_nb_sectors());
+            region_file.locations[index] = location;
+        }
+
+        Ok(region_file)
+    }
+
+    // a location pointing outside of the file or on top of another chunk is ignored
+    fn location_is_valid(&self, location: &ChunkLocation) -> bool {
+        let first_sector = location.get_first_sector();
+        let last_sector = first_sector + location.get_nb_sectors();
+
+        first_sector >= NB_HEADER_SECTORS
+            && last_sector <= self.used_sectors.len()
+            && self.sectors_are_free(first_sector, location.get_nb_sectors())
+    }
+
+    pub fn has_chunk(&self, chunk_pos: &ChunkCoordsInRegion) -> bool {
+        !self.locations[chunk_pos.get_index()].is_empty()
+    }
+
+    pub fn get_saved_chunks(&self) -> Vec<ChunkCoordsInRegion> {
+        let mut saved_chunks = Vec::new();
+        for (index, location) in self.locations.iter().enumerate() {
+            if !location.is_empty() {
+                saved_chunks.push(ChunkCoordsInRegion::from_index(index));
+            }
+        }
+        saved_chunks
+    }
+
+    pub fn read_chunk(&mut self, chunk_pos: &ChunkCoordsInRegion) -> Result<Option<Vec<u8>>> {
+        let location = self.locations[chunk_pos.get_index()];
+        if location.is_empty() {
+            return Ok(None);
+        }
+
+        let mut data = vec![0; location.length as usize];
+        let offset = location.get_first_sector() * SECTOR_SIZE;
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.read_exact(&mut data)?;
+        Ok(Some(data))
+    }
+
+    pub fn write_chunk(&mut self, chunk_pos: &ChunkCoordsInRegion, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return self.remove_chunk(chunk_pos);
+        }
+
+        let index = chunk_pos.get_index();
+        let old_location = self.locations[index];
+        let old_first_sector = old_location.get_first_sector();
+        let old_nb_sectors = old_location.get_nb_sectors();
+        let nb_sectors = get_nb_sectors_for(data.len());
+
+        let first_sector = if !old_location.is_empty() && nb_sectors <= old_nb_sectors {
+            // the chunk still fit where it is, it is written over its previous version
+            // and the sectors it doesn't need anymore are given back
+            self.free_sectors(old_first_sector + nb_sectors, old_nb_sectors - nb_sectors);
+            old_first_sector
+        } else if !old_location.is_empty()
+            && self.sectors_are_free(
+                old_first_sector + old_nb_sectors,
+                nb_sectors - old_nb_sectors,
+            )
+        {
+            // the chunk grew but the sectors right after it are free so it can stay in
+            // place, it is written over its previous version too
+            self.mark_sectors_as_used(
+                old_first_sector + old_nb_sectors,
+                nb_sectors - old_nb_sectors,
+            );
+            old_first_sector
+        } else {
+            // the chunk has to move, its old sectors are only freed once the new ones are
+            // allocated so the new version is never written over the previous one
+            let first_sector = self.allocate_sectors(nb_sectors);
+            if !old_location.is_empty() {
+                self.free_sectors(old_first_sector, old_nb_sectors);
+            }
+            first_sector
+        };
+
+        self.write_sectors(first_sector, data)?;
+        self.locations[index] = ChunkLocation {
+            first_sector: first_sector as u32,
+            length: data.len() as u32,
+        };
+        self.write_location(index)
+    }
+
+    pub fn remove_chunk(&mut self, chunk_pos: &ChunkCoordsInRegion) -> Result<()> {
+        let index = chunk_pos.get_index();
+        let location = self.locations[index];
+        if location.is_empty() {
+            return Ok(());
+        }
+
+        self.free_sectors(location.get_first_sector(), location.get_nb_sectors());
+        self.locations[index] = ChunkLocation::default();
+        self.write_location(index)
+    }
+
+    // the number of sectors lost between the chunks
+    pub fn get_nb_free_sectors(&self) -> usize {
+        self.used_sectors
+            .iter()
+            .filter(|is_used| !**is_used)
+            .count()
+    }
+
+    pub fn get_nb_sectors(&self) -> usize {
+        self.used_sectors.len()
+    }
+
+    // move every chunk toward the start of the file so there is no free
+    // sector left between them, then cut the end of the file
+    pub fn compact(&mut self) -> Result<()> {
+        let mut chunks_indices: Vec<usize> = (0..NB_CHUNKS_PER_REGION)
+            .filter(|index| !self.locations[*index].is_empty())
+            .collect();
+        chunks_indices.sort_by_key(|index| self.locations[*index].first_sector);
+
+        // chunks are only moved backward and in the order of the file,
+        // so a chunk never overwrite one that hasn't been moved yet
+        let mut next_free_sector = NB_HEADER_SECTORS;
+        for index in chunks_indices {
+            let location = self.locations[index];
+            if location.get_first_sector() != next_free_sector {
+                let chunk_pos = ChunkCoordsInRegion::from_index(index);
+                let data = self.read_chunk(&chunk_pos)?.unwrap();
+                self.write_sectors(next_free_sector, &data)?;
+                self.locations[index].first_sector = next_free_sector as u32;
+                self.write_location(index)?;
+            }
+            next_free_sector += location.get_nb_sectors();
+        }
+
+        self.file.set_len((next_free_sector * SECTOR_SIZE) as u64)?;
+        self.used_sectors = vec![true; next_free_sector];
+        self.file.sync_all()
+    }
+
+    fn write_location(&mut self, index: usize) -> Result<()> {
+        let offset = index * LOCATION_SIZE;
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.write_all(&self.locations[index].to_bytes())
+    }
+
+    // the last sector is padded with zeros so the file length stay a multiple of SECTOR_SIZE
+    fn write_sectors(&mut self, first_sector: usize, data: &[u8]) -> Result<()> {
+        let padding = get_nb_sectors_for(data.len()) * SECTOR_SIZE - data.len();
+        self.file
+            .seek(SeekFrom::Start((first_sector * SECTOR_SIZE) as u64))?;
+        self.file.write_all(data)?;
+        self.file.write_all(&vec![0; padding])
+    }
+
+    fn sectors_are_free(&self, first_sector: usize, nb_sectors: usize) -> bool {
+        (first_sector..first_sector + nb_sectors)
+            .all(|sector| sector >= self.used_sectors.len() || !self.used_sectors[sector])
+    }
+
+    fn mark_sectors_as_used(&mut self, first_sector: usize, nb_sectors: usize) {
+        let last_sector = first_sector + nb_sectors;
+        if last_sector > self.used_sectors.len() {
+            self.used_sectors.resize(last_sector, false);
+        }
+        for sector in first_sector..last_sector {
+            self.used_sectors[sector] = true;
+        }
+    }
+
+    fn free_sectors(&mut self, first_sector: usize, nb_sectors: usize) {
+        for sector in first_sector..first_sector + nb_sectors {
+            self.used_sectors[sector] = false;
+        }
+    }
+
+    // first fit, if no hole is big enough the chunk is put at the end of the file
+    fn allocate_sectors(&mut self, nb_sectors: usize) -> usize {
+        let mut run_start = NB_HEADER_SECTORS;
+        let mut run_length = 0;
+
+        for sector in NB_HEADER_SECTORS..self.used_sectors.len() {
+            if self.used_sectors[sector] {
+                run_start = sector + 1;
+                run_length = 0;
+                continue;
+            }
+
+            run_length += 1;
+            if run_length == nb_sectors {
+                break;
+            }
+        }
+
+        // when the loop ends on a free run too short, it is extended past the end of the file
+        self.mark_sectors_as_used(run_start, nb_sectors);
+        run_start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    // a region file of its own for each test, in the temporary directory
+    fn get_test_region_path(test_name: &str) -> PathBuf {
+        let file_name = format!("region_file_{}_{}.region", test_name, process::id());
+        let path = env::temp_dir().join(file_name);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn get_test_data(nb_sectors: usize, value: u8) -> Vec<u8> {
+        // one byte less than the sectors so the last one is padded
+        vec![value; nb_sectors * SECTOR_SIZE - 1]
+    }
+
+    fn get_first_sector(region: &RegionFile, chunk_pos: &ChunkCoordsInRegion) -> usize {
+        region.locations[chunk_pos.get_index()].get_first_sector()
+    }
+
+    #[test]
+    fn chunks_are_allocated_after_the_header() {
+        let path = get_test_region_path("allocation");
+        let mut region = RegionFile::open(&path).unwrap();
+        let (first_chunk, second_chunk) = (
+            ChunkCoordsInRegion::new(0, 0),
+            ChunkCoordsInRegion::new(3, 7),
+        );
+
+        region
+            .write_chunk(&first_chunk, &get_test_data(1, 1))
+            .unwrap();
+        region
+            .write_chunk(&second_chunk, &get_test_data(2, 2))
+            .unwrap();
+
+        assert_eq!(get_first_sector(&region, &first_chunk), NB_HEADER_SECTORS);
+        assert_eq!(
+            get_first_sector(&region, &second_chunk),
+            NB_HEADER_SECTORS + 1
+        );
+        assert_eq!(region.get_nb_sectors(), NB_HEADER_SECTORS + 3);
+        assert_eq!(
+            region.read_chunk(&first_chunk).unwrap(),
+            Some(get_test_data(1, 1))
+        );
+        assert_eq!(
+            region.read_chunk(&second_chunk).unwrap(),
+            Some(get_test_data(2, 2))
+        );
+        assert_eq!(
+            region.read_chunk(&ChunkCoordsInRegion::new(1, 1)).unwrap(),
+            None
+        );
+
+        // the header is read back when the file is opened again
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_eq!(region.get_saved_chunks(), vec![first_chunk, second_chunk]);
+        assert_eq!(
+            region.read_chunk(&second_chunk).unwrap(),
+            Some(get_test_data(2, 2))
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn chunks_grow_and_shrink_in_place() {
+        let path = get_test_region_path("in_place");
+        let mut region = RegionFile::open(&path).unwrap();
+        let chunk_pos = ChunkCoordsInRegion::new(2, 2);
+
+        region
+            .write_chunk(&chunk_pos, &get_test_data(1, 1))
+            .unwrap();
+        region
+            .write_chunk(&chunk_pos, &get_test_data(3, 2))
+            .unwrap();
+        assert_eq!(get_first_sector(&region, &chunk_pos), NB_HEADER_SECTORS);
+        assert_eq!(
+            region.read_chunk(&chunk_pos).unwrap(),
+            Some(get_test_data(3, 2))
+        );
+
+        region
+            .write_chunk(&chunk_pos, &get_test_data(1, 3))
+            .unwrap();
+        assert_eq!(get_first_sector(&region, &chunk_pos), NB_HEADER_SECTORS);
+        assert_eq!(region.get_nb_free_sectors(), 2);
+        assert_eq!(
+            region.read_chunk(&chunk_pos).unwrap(),
+            Some(get_test_data(1, 3))
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn chunks_move_when_they_cant_grow_in_place() {
+        let path = get_test_region_path("move");
+        let mut region = RegionFile::open(&path).unwrap();
+        let moved_chunk = ChunkCoordsInRegion::new(0, 1);
+        let next_chunk = ChunkCoordsInRegion::new(0, 2);
+        let small_chunk = ChunkCoordsInRegion::new(0, 3);
+
+        region
+            .write_chunk(&moved_chunk, &get_test_data(1, 1))
+            .unwrap();
+        region
+            .write_chunk(&next_chunk, &get_test_data(1, 2))
+            .unwrap();
+        region
+            .write_chunk(&moved_chunk, &get_test_data(2, 3))
+            .unwrap();
+
+        assert_eq!(
+            get_first_sector(&region, &moved_chunk),
+            NB_HEADER_SECTORS + 2
+        );
+        assert_eq!(region.get_nb_free_sectors(), 1);
+        assert_eq!(
+            region.read_chunk(&moved_chunk).unwrap(),
+            Some(get_test_data(2, 3))
+        );
+        assert_eq!(
+            region.read_chunk(&next_chunk).unwrap(),
+            Some(get_test_data(1, 2))
+        );
+
+        // the sector left by the moved chunk is the first one that fits
+        region
+            .write_chunk(&small_chunk, &get_test_data(1, 4))
+            .unwrap();
+        assert_eq!(get_first_sector(&region, &small_chunk), NB_HEADER_SECTORS);
+        assert_eq!(region.get_nb_free_sectors(), 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn compact_removes_the_free_sectors() {
+        let path = get_test_region_path("compact");
+        let mut region = RegionFile::open(&path).unwrap();
+        let chunks: Vec<ChunkCoordsInRegion> =
+            (0..4).map(|z| ChunkCoordsInRegion::new(5, z)).collect();
+        for (value, chunk_pos) in chunks.iter().enumerate() {
+            region
+                .write_chunk(chunk_pos, &get_test_data(2, value as u8))
+                .unwrap();
+        }
+        region.remove_chunk(&chunks[0]).unwrap();
+        region.remove_chunk(&chunks[2]).unwrap();
+        assert_eq!(region.get_nb_free_sectors(), 4);
+
+        region.compact().unwrap();
+        assert_eq!(region.get_nb_free_sectors(), 0);
+        assert_eq!(region.get_nb_sectors(), NB_HEADER_SECTORS + 4);
+        assert_eq!(
+            fs::metadata(&path).unwrap().len() as usize,
+            (NB_HEADER_SECTORS + 4) * SECTOR_SIZE
+        );
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_eq!(region.get_saved_chunks(), vec![chunks[1], chunks[3]]);
+        assert_eq!(
+            region.read_chunk(&chunks[1]).unwrap(),
+            Some(get_test_data(2, 1))
+        );
+        assert_eq!(
+            region.read_chunk(&chunks[3]).unwrap(),
+            Some(get_test_data(2, 3))
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_locations_of_the_header_are_ignored() {
+        let path = get_test_region_path("header");
+        let mut region = RegionFile::open(&path).unwrap();
+        let valid_chunk = ChunkCoordsInRegion::from_index(0);
+        region
+            .write_chunk(&valid_chunk, &get_test_data(1, 1))
+            .unwrap();
+
+        let invalid_locations = [
+            // in the header
+            ChunkLocation {
+                first_sector: 1,
+                length: 10,
+            },
+            // past the end of the file
+            ChunkLocation {
+                first_sector: 1000,
+                length: 10,
+            },
+            // on top of the valid chunk
+            ChunkLocation {
+                first_sector: NB_HEADER_SECTORS as u32,
+                length: 10,
+            },
+        ];
+        for (index, location) in invalid_locations.into_iter().enumerate() {
+            region.locations[index + 1] = location;
+            region.write_location(index + 1).unwrap();
+        }
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_eq!(region.get_saved_chunks(), vec![valid_chunk]);
+        assert_eq!(
+            region.read_chunk(&valid_chunk).unwrap(),
+            Some(get_test_data(1, 1))
+        );
+        assert_eq!(region.get_nb_free_sectors(), 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncated_header_is_reset() {
+        let path = get_test_region_path("truncated");
+        fs::write(&path, vec![0xFF; HEADER_SIZE / 2]).unwrap();
+
+        let region = RegionFile::open(&path).unwrap();
+        assert!(region.get_saved_chunks().is_empty());
+        assert_eq!(region.get_nb_sectors(), NB_HEADER_SECTORS);
+        fs::remove_file(&path).unwrap();
+    }
+}
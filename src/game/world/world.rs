@@ -1,8 +1,21 @@
+use super::block_registry::{BlockRegistry, BLOCKS_DEFINITIONS_PATH};
+use super::chunk_renderer::chunk_material::{ChunkMaterial, ChunkMaterials};
 use super::chunk_renderer::renderer::generate_chunk_mesh_system;
-use super::chunks_manager::{handle_generated_chunks_system, manage_chunks_system};
+use super::chunk_renderer::texture_atlas::{BlockTextureAtlas, BLOCKS_TEXTURES_DIRECTORY};
+use super::chunks_manager::{
+    handle_generated_chunks_system, manage_chunks_system, save_chunks_on_exit_system,
+};
+use super::coordinates::chunk_coordinates::ChunkCoordinates;
+use super::fluid_simulation::{update_fluids_system, FluidSimulation};
+use super::light::light_propagation::spread_light_between_chunks_system;
+use super::raycast::update_targeted_block_system;
+use super::storage::chunk_storage::{get_world_directory, ChunkStorage};
+use super::world_edit::break_and_place_blocks_system;
 use super::world_generator::generator::WorldGenerator;
+use crate::game::camera::cursor_grab_system;
 use bevy::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 use super::chunk::CHUNK_SIZE;
 pub const CHUNK_SIZE_I32: i32 = CHUNK_SIZE as i32;
@@ -18,21 +31,70 @@ pub struct Dimention;
 pub struct World {
     seed: u32,
     pub world_generator: WorldGenerator,
-    pub loaded_chunks: HashSet<(i32, i32, i32)>,
+    pub chunk_storage: ChunkStorage,
+    // the entity of each chunk loaded in the world
+    pub loaded_chunks: HashMap<(i32, i32, i32), Entity>,
     pub chunks_in_generation: HashSet<(i32, i32, i32)>,
+    pub chunks_in_loading: HashSet<(i32, i32, i32)>,
     pub chunks_to_render: HashSet<(i32, i32, i32)>,
+    // the chunks just loaded, their light has to spread from and into their neighbors
+    pub chunks_to_spread_light: HashSet<(i32, i32, i32)>,
+    pub fluid_simulation: FluidSimulation,
 }
 
 impl World {
-    pub fn new(seed: u32) -> Self {
+    pub fn new(seed: u32, block_registry: &BlockRegistry) -> Self {
         Self {
             seed,
-            world_generator: WorldGenerator::new(seed),
-            loaded_chunks: HashSet::new(),
+            world_generator: WorldGenerator::new(seed, block_registry),
+            chunk_storage: ChunkStorage::new(&get_world_name(seed)),
+            loaded_chunks: HashMap::new(),
             chunks_in_generation: HashSet::new(),
+            chunks_in_loading: HashSet::new(),
             chunks_to_render: HashSet::new(),
+            chunks_to_spread_light: HashSet::new(),
+            fluid_simulation: FluidSimulation::new(),
         }
     }
+
+    pub fn get_chunk_entity(&self, block_position: IVec3) -> Option<Entity> {
+        let chunk_coords = ChunkCoordinates::from_global_block_position(block_position);
+        self.loaded_chunks.get(&chunk_coords.to_tuple()).copied()
+    }
+
+    // the chunk of the block is rendered again, and so are the neighbor chunks
+    // when the block is on a border since their faces against it may change
+    pub fn mark_block_as_modified(&mut self, block_position: IVec3) {
+        let chunk_coords = ChunkCoordinates::from_global_block_position(block_position);
+        let (x, y, z) = chunk_coords.to_tuple();
+        self.chunks_to_render.insert((x, y, z));
+
+        let x_in_chunk = block_position.x - x;
+        let z_in_chunk = block_position.z - z;
+        let mut neighbor_chunks = Vec::new();
+        if x_in_chunk == 0 {
+            neighbor_chunks.push((x - CHUNK_SIZE_I32, y, z));
+        }
+        if x_in_chunk == CHUNK_SIZE_I32 - 1 {
+            neighbor_chunks.push((x + CHUNK_SIZE_I32, y, z));
+        }
+        if z_in_chunk == 0 {
+            neighbor_chunks.push((x, y, z - CHUNK_SIZE_I32));
+        }
+        if z_in_chunk == CHUNK_SIZE_I32 - 1 {
+            neighbor_chunks.push((x, y, z + CHUNK_SIZE_I32));
+        }
+
+        for neighbor_chunk in neighbor_chunks {
+            if self.loaded_chunks.contains_key(&neighbor_chunk) {
+                self.chunks_to_render.insert(neighbor_chunk);
+            }
+        }
+    }
+}
+
+pub fn get_world_name(seed: u32) -> String {
+    format!("world_{}", seed)
 }
 
 pub fn get_neighbor_chunks(chunk_coords: &(i32, i32, i32)) -> HashSet<(i32, i32, i32)> {
@@ -46,8 +108,37 @@ pub fn get_neighbor_chunks(chunk_coords: &(i32, i32, i32)) -> HashSet<(i32, i32,
     ])
 }
 
-fn spawn_world_system(mut commands: Commands) {
-    commands.spawn(World::new(0)).insert(SpatialBundle {
+fn spawn_world_system(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    let seed = 0;
+    // the blocks ids are saved with the world, so the registry is loaded for it
+    let world_directory = get_world_directory(&get_world_name(seed));
+    let definitions_path = Path::new(BLOCKS_DEFINITIONS_PATH);
+    let block_registry = BlockRegistry::load(definitions_path, &world_directory);
+
+    let textures_directory = Path::new(BLOCKS_TEXTURES_DIRECTORY);
+    let (texture_atlas, atlas_image) = BlockTextureAtlas::new(&block_registry, textures_directory);
+    let opaque_material = ChunkMaterial {
+        tile_size: texture_atlas.get_tile_size(),
+        atlas: images.add(atlas_image),
+        alpha_mode: AlphaMode::Opaque,
+    };
+    let translucent_material = ChunkMaterial {
+        alpha_mode: AlphaMode::Blend,
+        ..opaque_material.clone()
+    };
+    commands.insert_resource(ChunkMaterials {
+        opaque: chunk_materials.add(opaque_material),
+        translucent: chunk_materials.add(translucent_material),
+    });
+    commands.insert_resource(texture_atlas);
+    let world = World::new(seed, &block_registry);
+    commands.insert_resource(block_registry);
+
+    commands.spawn(world).insert(SpatialBundle {
         transform: Transform::from_xyz(0., 0., 0.),
         ..Default::default()
     });
@@ -55,9 +146,21 @@ fn spawn_world_system(mut commands: Commands) {
 
 impl Plugin for Dimention {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_world_system)
+        app.add_plugin(MaterialPlugin::<ChunkMaterial>::default())
+            .add_startup_system(spawn_world_system)
             .add_system(generate_chunk_mesh_system)
             .add_system(manage_chunks_system.after(generate_chunk_mesh_system))
-            .add_system(handle_generated_chunks_system.after(generate_chunk_mesh_system));
+            .add_system(handle_generated_chunks_system.after(generate_chunk_mesh_system))
+            // the chunks lit by the light coming from their neighbors are meshed on the next frame
+            .add_system(spread_light_between_chunks_system.after(handle_generated_chunks_system))
+            .add_system(update_fluids_system.before(generate_chunk_mesh_system))
+            .add_system(update_targeted_block_system)
+            .add_system(
+                break_and_place_blocks_system
+                    .after(update_targeted_block_system)
+                    .before(cursor_grab_system)
+                    .before(generate_chunk_mesh_system),
+            )
+            .add_system(save_chunks_on_exit_system.in_base_set(CoreSet::Last));
     }
 }
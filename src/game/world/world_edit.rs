@@ -0,0 +1,178 @@
+use super::block::{Block, BlockOrientation};
+use super::block_registry::BlockRegistry;
+use super::chunk::Chunk;
+use super::chunk_renderer::blocks_resources::blocks_ids::{AIR_BLOCK, DIRT_BLOCK};
+use super::chunk_renderer::blocks_resources::OrientationMode;
+use super::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+use super::coordinates::chunk_coordinates::ChunkCoordinates;
+use super::light::light_propagation::{update_light_after_edit, LightedChunks};
+use super::loaded_chunks::get_loaded_block;
+use super::raycast::TargetedBlock;
+use super::world::{World, CHUNK_SIZE_I32};
+use crate::game::camera::{get_camera_ray, FirstPersonCamera, Position, Rotation};
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+// the block put by a right click
+const PLACED_BLOCK: u16 = DIRT_BLOCK;
+
+// the block at this position for the systems editing the chunks, see get_loaded_block
+pub fn get_block_at(
+    world: &World,
+    chunks: &Query<&mut Chunk>,
+    block_position: IVec3,
+) -> Option<Block> {
+    get_loaded_block(world, |entity| chunks.get(entity).ok(), block_position)
+}
+
+// The chunk of the block is marked as dirty, the light around the block is
+// updated, the fluids around it are scheduled to flow and the chunks changed
+// are rendered again. Return false when the block can't be changed because its
+// chunk is not loaded or it is outside of the world.
+pub fn set_block_at(
+    world: &mut World,
+    chunks: &mut Query<&mut Chunk>,
+    block_registry: &BlockRegistry,
+    block_position: IVec3,
+    block: Block,
+) -> bool {
+    if !set_block_at_without_light(world, chunks, block_position, block) {
+        return false;
+    }
+    update_light_around_blocks(world, chunks, block_registry, &[block_position]);
+    true
+}
+
+// Like set_block_at, but the light is left as it is so the light of many blocks
+// can be updated at once with update_light_around_blocks.
+pub fn set_block_at_without_light(
+    world: &mut World,
+    chunks: &mut Query<&mut Chunk>,
+    block_position: IVec3,
+    block: Block,
+) -> bool {
+    let Some(block_coords) = BlockCoordsInChunk::from_global_block_position(block_position) else {
+        return false;
+    };
+    let Some(chunk_entity) = world.get_chunk_entity(block_position) else {
+        return false;
+    };
+    let Ok(mut chunk) = chunks.get_mut(chunk_entity) else {
+        return false;
+    };
+
+    chunk.set_block(block_coords.get_block_index(), block);
+    world.mark_block_as_modified(block_position);
+    // the fluids around may have to flow into the block or dry up
+    world
+        .fluid_simulation
+        .schedule_update_around(block_position);
+    true
+}
+
+// Update the light around the blocks changed by set_block_at_without_light, the
+// chunks around them are gathered once for all of them. The light can't go
+// further than a chunk away from a block.
+pub fn update_light_around_blocks(
+    world: &mut World,
+    chunks: &mut Query<&mut Chunk>,
+    block_registry: &BlockRegistry,
+    block_positions: &[IVec3],
+) {
+    if block_positions.is_empty() {
+        return;
+    }
+
+    let mut chunks_coords = HashSet::new();
+    for block_position in block_positions {
+        let (x, y, z) = ChunkCoordinates::from_global_block_position(*block_position).to_tuple();
+        for offset_x in [-CHUNK_SIZE_I32, 0, CHUNK_SIZE_I32] {
+            for offset_z in [-CHUNK_SIZE_I32, 0, CHUNK_SIZE_I32] {
+                chunks_coords.insert((x + offset_x, y, z + offset_z));
+            }
+        }
+    }
+
+    let mut lighted_chunks = LightedChunks::new();
+    for chunk in chunks.iter_mut() {
+        if chunks_coords.contains(&chunk.get_coords().to_tuple()) {
+            lighted_chunks.insert(chunk);
+        }
+    }
+    for block_position in block_positions {
+        update_light_after_edit(&mut lighted_chunks, *block_position, block_registry);
+    }
+    let modified_chunks = lighted_chunks.get_modified_chunks().clone();
+    world.chunks_to_render.extend(modified_chunks);
+}
+
+// left click break the targeted block, right click place a block against its face,
+// a block can only replace air or a fluid
+pub fn break_and_place_blocks_system(
+    mouse_buttons: Res<Input<MouseButton>>,
+    block_registry: Res<BlockRegistry>,
+    mut worlds: Query<&mut World>,
+    mut chunks: Query<&mut Chunk>,
+    cameras: Query<(&FirstPersonCamera, &TargetedBlock, &Position, &Rotation)>,
+) {
+    let Ok(mut world) = worlds.get_single_mut() else {
+        return;
+    };
+
+    for (camera, targeted_block, position, rotation) in cameras.iter() {
+        let Some(hit) = targeted_block.0 else {
+            continue;
+        };
+        if !camera.has_focus {
+            continue;
+        }
+
+        if mouse_buttons.just_pressed(MouseButton::Left) {
+            set_block_at(
+                &mut world,
+                &mut chunks,
+                &block_registry,
+                hit.block_position,
+                Block::default(),
+            );
+        } else if mouse_buttons.just_pressed(MouseButton::Right) && hit.normal != IVec3::ZERO {
+            let (_, look_direction) = get_camera_ray(position, rotation);
+            let orientation = get_placed_block_orientation(
+                block_registry.get_orientation_mode(PLACED_BLOCK),
+                hit.normal,
+                look_direction,
+            );
+            let block = Block::new(PLACED_BLOCK, orientation);
+            set_block_at(
+                &mut world,
+                &mut chunks,
+                &block_registry,
+                hit.block_position + hit.normal,
+                block,
+            );
+        }
+    }
+}
+
+// The top of an Axis block points away from the face it is placed against,
+// the front of a Facing block looks back at the player along the horizontal
+// axis closest to the direction the player is looking at.
+fn get_placed_block_orientation(
+    orientation_mode: OrientationMode,
+    hit_normal: IVec3,
+    look_direction: Vec3,
+) -> BlockOrientation {
+    match orientation_mode {
+        OrientationMode::Fixed => BlockOrientation::default(),
+        OrientationMode::Axis => BlockOrientation::from_direction(hit_normal).unwrap_or_default(),
+        OrientationMode::Facing => {
+            let toward_player = -look_direction;
+            let front = if toward_player.x.abs() >= toward_player.z.abs() {
+                IVec3::new(toward_player.x.signum() as i32, 0, 0)
+            } else {
+                IVec3::new(0, 0, toward_player.z.signum() as i32)
+            };
+            BlockOrientation::from_direction(front).unwrap_or_default()
+        }
+    }
+}
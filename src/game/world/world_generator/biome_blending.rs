@@ -0,0 +1,79 @@
+use super::biomes::biome::BIOME_SIZE_IN_BLOCKS;
+use crate::game::world::coordinates::biome_coordinates::BiomeCoordinates;
+use crate::game::world::coordinates::global_coordinates::GlobalCoordinates;
+
+// how many blocks it takes to go from one biome to its neighbor
+pub const DEFAULT_BIOME_TRANSITION_WIDTH: u32 = 64;
+
+pub struct WeightedBiomeCoordinates {
+    pub biome_coords: BiomeCoordinates,
+    pub weight: f64,
+}
+
+// Give how much each biome near a column of blocks contribute to it.
+// Along each axis a column less than half the transition width away from the
+// border of its biome is shared with the biome on the other side of the border,
+// the weights are the same on both sides of the border so the terrain is continuous.
+#[derive(Clone)]
+pub struct BiomeBlender {
+    transition_width: u32,
+}
+
+impl BiomeBlender {
+    // a transition wider than a biome would blend both of its borders at once
+    pub fn new(transition_width: u32) -> Self {
+        assert!(transition_width as usize <= BIOME_SIZE_IN_BLOCKS);
+        Self { transition_width }
+    }
+
+    // the weights always add up to 1
+    pub fn get_weighted_biomes(&self, coords: &GlobalCoordinates) -> Vec<WeightedBiomeCoordinates> {
+        let biome_coords = BiomeCoordinates::from_global_coordinates(coords);
+        let [pos_x, pos_z] = biome_coords.get_pos_in_biome(coords);
+        let (weight_x, neighbor_x) = self.get_weight_along_axis(pos_x);
+        let (weight_z, neighbor_z) = self.get_weight_along_axis(pos_z);
+
+        let candidates = [
+            (0, 0, weight_x * weight_z),
+            (neighbor_x, 0, (1. - weight_x) * weight_z),
+            (0, neighbor_z, weight_x * (1. - weight_z)),
+            (neighbor_x, neighbor_z, (1. - weight_x) * (1. - weight_z)),
+        ];
+
+        let mut weighted_biomes = Vec::with_capacity(candidates.len());
+        for (offset_x, offset_z, weight) in candidates {
+            if weight <= 0. {
+                continue;
+            }
+            weighted_biomes.push(WeightedBiomeCoordinates {
+                biome_coords: biome_coords.get_neighbor(offset_x, offset_z),
+                weight,
+            });
+        }
+        weighted_biomes
+    }
+
+    // returns the weight of the biome containing the position and the direction
+    // of the neighbor biome sharing the rest of the weight (0 if there is none)
+    fn get_weight_along_axis(&self, pos_in_biome: f64) -> (f64, i32) {
+        let half_transition_width = self.transition_width as f64 / 2.;
+        // the weight is computed at the center of the block
+        let distance_to_negative_border = pos_in_biome + 0.5;
+        let distance_to_positive_border = BIOME_SIZE_IN_BLOCKS as f64 - distance_to_negative_border;
+
+        if distance_to_negative_border < half_transition_width {
+            let weight = 0.5 + distance_to_negative_border / self.transition_width as f64;
+            (smoothstep(weight), -1)
+        } else if distance_to_positive_border < half_transition_width {
+            let weight = 0.5 + distance_to_positive_border / self.transition_width as f64;
+            (smoothstep(weight), 1)
+        } else {
+            (1., 0)
+        }
+    }
+}
+
+// smoothstep(1 - x) = 1 - smoothstep(x), so both sides of a border still add up to 1
+fn smoothstep(x: f64) -> f64 {
+    x * x * (3. - 2. * x)
+}
@@ -1,25 +1,134 @@
 use super::desert_biome::DesertBiome;
 use super::grass_hills_biome::GrassHillsBiome;
+use super::sky_islands_biome::SkyIslandsBiome;
 use crate::game::world::{
-    chunk::{AbleToGenerateChunk, Chunk, CHUNK_SIZE},
-    world_generator::generator::WorldGenNoises,
+    block::Block,
+    chunk::CHUNK_SIZE,
+    world_generator::{
+        caves::CavesSettings, decorations::DecorationSettings, generator::WorldGenNoises,
+    },
 };
 
 pub const BIOME_SIZE_IN_CHUNKS: usize = 128;
 pub const BIOME_SIZE_IN_BLOCKS: usize = CHUNK_SIZE * BIOME_SIZE_IN_CHUNKS;
 pub const OCEAN_LEVEL: u32 = 48;
 
+// A biome describe its terrain one column of blocks at a time so the
+// terrain of neighbor biomes can be blended together
+pub trait AbleToGenerateTerrain {
+    // the height of the terrain in blocks, pos_in_biome is relative to the corner
+    // of the biome and can be outside of it when it is blended with a neighbor biome
+    fn get_terrain_height(
+        &self,
+        global_pos: [f64; 2],
+        pos_in_biome: [f64; 2],
+        noises: &WorldGenNoises,
+    ) -> f64;
+
+    fn get_block_at(&self, y: u32, terrain_height: f64) -> Block;
+
+    // how much the terrain of the biome is carved by caves
+    fn get_caves_settings(&self) -> CavesSettings;
+
+    // the trees and plants growing on the surface of the biome
+    fn get_decorations(&self) -> &'static [DecorationSettings];
+
+    // The density of the terrain floating above the ground, a block of air is filled
+    // where it is above 0. None when the biome has nothing floating.
+    fn get_floating_density(
+        &self,
+        global_pos: [f64; 3],
+        pos_in_biome: [f64; 2],
+        noises: &WorldGenNoises,
+    ) -> Option<f64>;
+}
+
 #[derive(Clone)]
 pub enum Biome {
     Desert(DesertBiome),
     GrassHills(GrassHillsBiome),
+    SkyIslands(SkyIslandsBiome),
+}
+
+// the chance for a biome to be picked is its weight divided by the sum of all the weights
+const BIOMES_WEIGHTS: [(Biome, u64); 3] = [
+    (Biome::Desert(DesertBiome), 1),
+    (Biome::GrassHills(GrassHillsBiome), 2),
+    (Biome::SkyIslands(SkyIslandsBiome), 1),
+];
+
+impl Biome {
+    // map a uniformly distributed value to a biome according to the weights
+    pub fn from_weighted_value(value: u64) -> Self {
+        let total_weight: u64 = BIOMES_WEIGHTS.iter().map(|(_, weight)| weight).sum();
+        let mut remaining_weight = value % total_weight;
+
+        for (biome, weight) in BIOMES_WEIGHTS {
+            if remaining_weight < weight {
+                return biome;
+            }
+            remaining_weight -= weight;
+        }
+        unreachable!()
+    }
 }
 
-impl AbleToGenerateChunk for Biome {
-    fn generate_chunk(&self, chunk: &mut Chunk, noises: WorldGenNoises) {
+impl AbleToGenerateTerrain for Biome {
+    fn get_terrain_height(
+        &self,
+        global_pos: [f64; 2],
+        pos_in_biome: [f64; 2],
+        noises: &WorldGenNoises,
+    ) -> f64 {
+        match self {
+            Biome::Desert(desert) => desert.get_terrain_height(global_pos, pos_in_biome, noises),
+            Biome::GrassHills(grass_hills) => {
+                grass_hills.get_terrain_height(global_pos, pos_in_biome, noises)
+            }
+            Biome::SkyIslands(sky_islands) => {
+                sky_islands.get_terrain_height(global_pos, pos_in_biome, noises)
+            }
+        }
+    }
+
+    fn get_block_at(&self, y: u32, terrain_height: f64) -> Block {
+        match self {
+            Biome::Desert(desert) => desert.get_block_at(y, terrain_height),
+            Biome::GrassHills(grass_hills) => grass_hills.get_block_at(y, terrain_height),
+            Biome::SkyIslands(sky_islands) => sky_islands.get_block_at(y, terrain_height),
+        }
+    }
+
+    fn get_caves_settings(&self) -> CavesSettings {
+        match self {
+            Biome::Desert(desert) => desert.get_caves_settings(),
+            Biome::GrassHills(grass_hills) => grass_hills.get_caves_settings(),
+            Biome::SkyIslands(sky_islands) => sky_islands.get_caves_settings(),
+        }
+    }
+
+    fn get_decorations(&self) -> &'static [DecorationSettings] {
+        match self {
+            Biome::Desert(desert) => desert.get_decorations(),
+            Biome::GrassHills(grass_hills) => grass_hills.get_decorations(),
+            Biome::SkyIslands(sky_islands) => sky_islands.get_decorations(),
+        }
+    }
+
+    fn get_floating_density(
+        &self,
+        global_pos: [f64; 3],
+        pos_in_biome: [f64; 2],
+        noises: &WorldGenNoises,
+    ) -> Option<f64> {
         match self {
-            Biome::Desert(desert) => desert.generate_chunk(chunk, noises),
-            Biome::GrassHills(grass_hills) => grass_hills.generate_chunk(chunk, noises),
+            Biome::Desert(desert) => desert.get_floating_density(global_pos, pos_in_biome, noises),
+            Biome::GrassHills(grass_hills) => {
+                grass_hills.get_floating_density(global_pos, pos_in_biome, noises)
+            }
+            Biome::SkyIslands(sky_islands) => {
+                sky_islands.get_floating_density(global_pos, pos_in_biome, noises)
+            }
         }
     }
 }
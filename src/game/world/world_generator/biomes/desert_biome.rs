@@ -1,32 +1,38 @@
-use noise::{NoiseFn, ScaleBias, SuperSimplex};
+use noise::{NoiseFn, ScaleBias};
 
 use crate::game::world::block::{Block, BlockOrientation};
-use crate::game::world::chunk::{AbleToGenerateChunk, Chunk};
+use crate::game::world::chunk::CHUNK_HEIGHT;
 use crate::game::world::chunk_renderer::blocks_resources::blocks_ids::{
-    AIR_BLOCK, SAND_BLOCK, WATER_BLOCK,
+    AIR_BLOCK, SAND_BLOCK, STONE_BLOCK, WATER_BLOCK,
 };
-use crate::game::world::coordinates::block_coords_in_biome::BlockCoordsInBiome;
-use crate::game::world::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
-use crate::game::world::coordinates::global_coordinates::GlobalCoordinates;
+use crate::game::world::world_generator::caves::CavesSettings;
+use crate::game::world::world_generator::decorations::DecorationSettings;
 use crate::game::world::world_generator::generator::WorldGenNoises;
+use crate::game::world::world_generator::noise_layers::{NoiseLayer, DUNES};
 
-use super::biome::{BIOME_SIZE_IN_BLOCKS, OCEAN_LEVEL};
+use super::biome::{AbleToGenerateTerrain, BIOME_SIZE_IN_BLOCKS, OCEAN_LEVEL};
 use super::erosion::circular_island_fading_edges::CircularIslandFadingEdges;
 
+// the sand is deeper than the dirt of the other biomes, the stone is under it
+const SAND_DEPTH: f64 = 6.;
+
 #[derive(Clone)]
 pub struct DesertBiome;
 
-impl AbleToGenerateChunk for DesertBiome {
-    fn generate_chunk(&self, chunk: &mut Chunk, noises: WorldGenNoises) {
-        let chunk_coords = chunk.get_coords();
-        let blocks = chunk.as_mut_slice();
+impl AbleToGenerateTerrain for DesertBiome {
+    fn get_terrain_height(
+        &self,
+        global_pos: [f64; 2],
+        pos_in_biome: [f64; 2],
+        noises: &WorldGenNoises,
+    ) -> f64 {
         let mut scale = 0.5; // multiply by 0.5 to set the range in [-0.5; 0.5]
         let mut bias = 0.5; // offset by 0.5 to push the range to [0;1]
         bias -= 0.1; // offset the land level so there is no laque of water on the land
         scale -= 0.35; // flatten the relief so it looks more like dunes
 
-        let desert_dunes_noise: ScaleBias<f64, SuperSimplex, 2> =
-            ScaleBias::new(noises.super_simplex)
+        let desert_dunes_noise: ScaleBias<f64, &NoiseLayer, 2> =
+            ScaleBias::new(noises.get_layer(DUNES))
                 .set_scale(scale)
                 .set_bias(bias);
 
@@ -36,29 +42,46 @@ impl AbleToGenerateChunk for DesertBiome {
         let desert_shape =
             CircularIslandFadingEdges::new(island_center_x, island_center_z, island_radius);
 
-        for (i, mut block) in blocks.iter_mut().enumerate() {
-            let pos_in_chunk = BlockCoordsInChunk::from_block_index(i);
-            let g_pos = GlobalCoordinates::from_block_coordinates(&chunk_coords, &pos_in_chunk);
-
-            let mut coord_array = g_pos.to_2d_f64_array();
-            coord_array[0] /= 300.0; // asymetric stretching so it produce a dune pushed by the wind like shape
-            coord_array[1] /= 150.0;
-            let noise_value = desert_dunes_noise.get(coord_array);
-
-            let biome_pos = BlockCoordsInBiome::from_global_coordinates(&g_pos);
-            let erosion_value = desert_shape.get(biome_pos.to_2d_f64_array());
+        let mut coord_array = global_pos;
+        coord_array[0] /= 2.0; // asymetric stretching so it produce a dune pushed by the wind like shape
+        let noise_value = desert_dunes_noise.get(coord_array);
+        let erosion_value = desert_shape.get(pos_in_biome);
 
-            let final_terrain_value = noise_value * erosion_value;
+        noise_value * erosion_value * CHUNK_HEIGHT as f64
+    }
 
-            if g_pos.get_y() as f64 > final_terrain_value * 256. {
-                if g_pos.get_y() <= OCEAN_LEVEL {
-                    block.0 = Block::new(WATER_BLOCK, BlockOrientation::PositiveX).0;
-                } else {
-                    block.0 = Block::new(AIR_BLOCK, BlockOrientation::PositiveX).0;
-                }
+    fn get_block_at(&self, y: u32, terrain_height: f64) -> Block {
+        if y as f64 > terrain_height {
+            if y <= OCEAN_LEVEL {
+                Block::new(WATER_BLOCK, BlockOrientation::PositiveX)
             } else {
-                block.0 = Block::new(SAND_BLOCK, BlockOrientation::PositiveX).0;
+                Block::new(AIR_BLOCK, BlockOrientation::PositiveX)
             }
+        } else if (y as f64) <= terrain_height - SAND_DEPTH {
+            Block::new(STONE_BLOCK, BlockOrientation::PositiveX)
+        } else {
+            Block::new(SAND_BLOCK, BlockOrientation::PositiveX)
         }
     }
+
+    // only a few narrow tunnels under the sand
+    fn get_caves_settings(&self) -> CavesSettings {
+        CavesSettings {
+            cheese_threshold: 1.,
+            worm_radius: 0.04,
+        }
+    }
+
+    fn get_decorations(&self) -> &'static [DecorationSettings] {
+        &[]
+    }
+
+    fn get_floating_density(
+        &self,
+        _global_pos: [f64; 3],
+        _pos_in_biome: [f64; 2],
+        _noises: &WorldGenNoises,
+    ) -> Option<f64> {
+        None
+    }
 }
@@ -1,32 +1,45 @@
-use noise::{NoiseFn, ScaleBias, SuperSimplex};
+use noise::{NoiseFn, ScaleBias};
 
 use crate::game::world::block::{Block, BlockOrientation};
-use crate::game::world::chunk::{AbleToGenerateChunk, Chunk};
+use crate::game::world::chunk::CHUNK_HEIGHT;
 use crate::game::world::chunk_renderer::blocks_resources::blocks_ids::{
-    AIR_BLOCK, GRASS_BLOCK, SAND_BLOCK, WATER_BLOCK,
+    AIR_BLOCK, DIRT_BLOCK, GRASS_BLOCK, SAND_BLOCK, STONE_BLOCK, WATER_BLOCK,
 };
-use crate::game::world::coordinates::block_coords_in_biome::BlockCoordsInBiome;
-use crate::game::world::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
-use crate::game::world::coordinates::global_coordinates::GlobalCoordinates;
+use crate::game::world::world_generator::caves::CavesSettings;
+use crate::game::world::world_generator::decorations::{DecorationKind, DecorationSettings};
 use crate::game::world::world_generator::generator::WorldGenNoises;
+use crate::game::world::world_generator::noise_layers::{
+    NoiseLayer, CONTINENTALNESS, DETAIL, EROSION,
+};
 
-use super::biome::{BIOME_SIZE_IN_BLOCKS, OCEAN_LEVEL};
+use super::biome::{AbleToGenerateTerrain, BIOME_SIZE_IN_BLOCKS, OCEAN_LEVEL};
 use super::erosion::circular_island_fading_edges::CircularIslandFadingEdges;
 
+// the blocks of dirt (or sand on the beaches) between the surface and the stone
+const SOIL_DEPTH: f64 = 4.;
+
 #[derive(Clone)]
 pub struct GrassHillsBiome;
 
-impl AbleToGenerateChunk for GrassHillsBiome {
-    fn generate_chunk(&self, chunk: &mut Chunk, noises: WorldGenNoises) {
-        let chunk_coords = chunk.get_coords();
-        let blocks = chunk.as_mut_slice();
+impl AbleToGenerateTerrain for GrassHillsBiome {
+    fn get_terrain_height(
+        &self,
+        global_pos: [f64; 2],
+        pos_in_biome: [f64; 2],
+        noises: &WorldGenNoises,
+    ) -> f64 {
         let scale = 0.5; // multiply by 0.5 to set the range in [-0.5; 0.5]
         let bias = 0.5; // offset by 0.5 to push the range to [0;1]
 
-        let grasshills_noise: ScaleBias<f64, SuperSimplex, 2> =
-            ScaleBias::new(noises.super_simplex)
+        let grasshills_noise: ScaleBias<f64, &NoiseLayer, 2> =
+            ScaleBias::new(noises.get_layer(CONTINENTALNESS))
                 .set_scale(scale)
                 .set_bias(bias);
+        // the rough places get more of the detail layer
+        let roughness: ScaleBias<f64, &NoiseLayer, 2> = ScaleBias::new(noises.get_layer(EROSION))
+            .set_scale(scale)
+            .set_bias(bias);
+        let detail = noises.get_layer(DETAIL);
 
         let island_center_x = BIOME_SIZE_IN_BLOCKS as u32 / 2;
         let island_center_z = island_center_x;
@@ -34,33 +47,57 @@ impl AbleToGenerateChunk for GrassHillsBiome {
         let erosion =
             CircularIslandFadingEdges::new(island_center_x, island_center_z, island_radius);
 
-        for (i, mut block) in blocks.iter_mut().enumerate() {
-            let pos_in_chunk = BlockCoordsInChunk::from_block_index(i);
-            let g_pos = GlobalCoordinates::from_block_coordinates(&chunk_coords, &pos_in_chunk);
-
-            let mut coord_array = g_pos.to_2d_f64_array();
-            coord_array[0] /= 200.0;
-            coord_array[1] /= 200.0;
-            let noise_value = grasshills_noise.get(coord_array);
+        let detail_value = detail.get(global_pos) * roughness.get(global_pos) * 0.15;
+        let noise_value = (grasshills_noise.get(global_pos) + detail_value).clamp(0., 1.);
+        let erosion_value = erosion.get(pos_in_biome);
 
-            let biome_pos = BlockCoordsInBiome::from_global_coordinates(&g_pos);
-            let erosion_value = erosion.get(biome_pos.to_2d_f64_array());
-
-            let final_terrain_value = noise_value * erosion_value;
+        noise_value * erosion_value * CHUNK_HEIGHT as f64
+    }
 
-            if g_pos.get_y() as f64 > final_terrain_value * 256. {
-                if g_pos.get_y() <= OCEAN_LEVEL {
-                    block.0 = Block::new(WATER_BLOCK, BlockOrientation::PositiveX).0;
-                } else {
-                    block.0 = Block::new(AIR_BLOCK, BlockOrientation::PositiveX).0;
-                }
+    fn get_block_at(&self, y: u32, terrain_height: f64) -> Block {
+        if y as f64 > terrain_height {
+            if y <= OCEAN_LEVEL {
+                Block::new(WATER_BLOCK, BlockOrientation::PositiveX)
             } else {
-                if g_pos.get_y() >= OCEAN_LEVEL + 2 {
-                    block.0 = Block::new(GRASS_BLOCK, BlockOrientation::PositiveX).0;
-                } else {
-                    block.0 = Block::new(SAND_BLOCK, BlockOrientation::PositiveX).0;
-                }
+                Block::new(AIR_BLOCK, BlockOrientation::PositiveX)
             }
+        } else if (y as f64) <= terrain_height - SOIL_DEPTH {
+            Block::new(STONE_BLOCK, BlockOrientation::PositiveX)
+        } else if y < OCEAN_LEVEL + 2 {
+            Block::new(SAND_BLOCK, BlockOrientation::PositiveX)
+        } else if y as f64 > terrain_height - 1. {
+            Block::new(GRASS_BLOCK, BlockOrientation::PositiveX)
+        } else {
+            Block::new(DIRT_BLOCK, BlockOrientation::PositiveX)
+        }
+    }
+
+    fn get_caves_settings(&self) -> CavesSettings {
+        CavesSettings {
+            cheese_threshold: 0.55,
+            worm_radius: 0.06,
         }
     }
+
+    fn get_decorations(&self) -> &'static [DecorationSettings] {
+        &[
+            DecorationSettings {
+                kind: DecorationKind::Tree,
+                density: 0.008,
+            },
+            DecorationSettings {
+                kind: DecorationKind::Bush,
+                density: 0.015,
+            },
+        ]
+    }
+
+    fn get_floating_density(
+        &self,
+        _global_pos: [f64; 3],
+        _pos_in_biome: [f64; 2],
+        _noises: &WorldGenNoises,
+    ) -> Option<f64> {
+        None
+    }
 }
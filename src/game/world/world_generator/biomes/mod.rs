@@ -2,3 +2,4 @@ pub mod biome;
 pub mod desert_biome;
 pub mod erosion;
 pub mod grass_hills_biome;
+pub mod sky_islands_biome;
@@ -0,0 +1,113 @@
+use noise::{NoiseFn, ScaleBias};
+
+use crate::game::world::block::{Block, BlockOrientation};
+use crate::game::world::chunk_renderer::blocks_resources::blocks_ids::{
+    AIR_BLOCK, DIRT_BLOCK, GRASS_BLOCK, SAND_BLOCK, STONE_BLOCK, WATER_BLOCK,
+};
+use crate::game::world::world_generator::caves::CavesSettings;
+use crate::game::world::world_generator::decorations::DecorationSettings;
+use crate::game::world::world_generator::generator::WorldGenNoises;
+use crate::game::world::world_generator::noise_layers::{NoiseLayer, DETAIL, SKY_ISLANDS};
+
+use super::biome::{AbleToGenerateTerrain, BIOME_SIZE_IN_BLOCKS, OCEAN_LEVEL};
+use super::erosion::circular_island_fading_edges::CircularIslandFadingEdges;
+
+// the ground of the biome is the bottom of a shallow sea
+const SEA_FLOOR_HEIGHT: f64 = OCEAN_LEVEL as f64 - 12.;
+const SAND_DEPTH: f64 = 4.;
+
+// The islands are the widest at ISLANDS_HEIGHT. Above it they end after a few blocks
+// so their tops are almost flat, below it they narrow slowly down to a point.
+const ISLANDS_HEIGHT: f64 = 170.;
+const ISLANDS_TOP_THICKNESS: f64 = 24.;
+const ISLANDS_UNDERSIDE_DEPTH: f64 = 120.;
+// the noise must be above this value (in [0; 1]) at ISLANDS_HEIGHT to make an island
+const ISLANDS_THRESHOLD: f64 = 0.55;
+// the noise is stretched vertically so the islands are thick blocks and not thin layers
+const ISLANDS_VERTICAL_STRETCH: f64 = 2.;
+// the blocks of dirt under the grass of the islands, the rest is stone
+const ISLANDS_SOIL_DEPTH: u32 = 3;
+
+#[derive(Clone)]
+pub struct SkyIslandsBiome;
+
+impl AbleToGenerateTerrain for SkyIslandsBiome {
+    fn get_terrain_height(
+        &self,
+        global_pos: [f64; 2],
+        _pos_in_biome: [f64; 2],
+        noises: &WorldGenNoises,
+    ) -> f64 {
+        SEA_FLOOR_HEIGHT + noises.get_layer(DETAIL).get(global_pos) * 4.
+    }
+
+    fn get_block_at(&self, y: u32, terrain_height: f64) -> Block {
+        if y as f64 > terrain_height {
+            if y <= OCEAN_LEVEL {
+                Block::new(WATER_BLOCK, BlockOrientation::PositiveX)
+            } else {
+                Block::new(AIR_BLOCK, BlockOrientation::PositiveX)
+            }
+        } else if (y as f64) <= terrain_height - SAND_DEPTH {
+            Block::new(STONE_BLOCK, BlockOrientation::PositiveX)
+        } else {
+            Block::new(SAND_BLOCK, BlockOrientation::PositiveX)
+        }
+    }
+
+    // the sea floor is too close to the water to be carved
+    fn get_caves_settings(&self) -> CavesSettings {
+        CavesSettings::NONE
+    }
+
+    fn get_decorations(&self) -> &'static [DecorationSettings] {
+        &[]
+    }
+
+    // the islands are only over the center of the biome, like the islands of the
+    // other biomes they fade away before its edges
+    fn get_floating_density(
+        &self,
+        global_pos: [f64; 3],
+        pos_in_biome: [f64; 2],
+        noises: &WorldGenNoises,
+    ) -> Option<f64> {
+        let [x, y, z] = global_pos;
+        let falloff = if y >= ISLANDS_HEIGHT {
+            ((y - ISLANDS_HEIGHT) / ISLANDS_TOP_THICKNESS).powi(2)
+        } else {
+            (ISLANDS_HEIGHT - y) / ISLANDS_UNDERSIDE_DEPTH
+        };
+        // the noise can't be high enough to make an island here
+        if falloff >= 1. - ISLANDS_THRESHOLD {
+            return Some(1. - ISLANDS_THRESHOLD - falloff);
+        }
+
+        let islands_noise: ScaleBias<f64, &NoiseLayer, 3> =
+            ScaleBias::new(noises.get_layer(SKY_ISLANDS))
+                .set_scale(0.5)
+                .set_bias(0.5);
+
+        let island_center_x = BIOME_SIZE_IN_BLOCKS as u32 / 2;
+        let island_center_z = island_center_x;
+        let island_radius = BIOME_SIZE_IN_BLOCKS as u32 / 5;
+        let islands_area =
+            CircularIslandFadingEdges::new(island_center_x, island_center_z, island_radius);
+
+        let noise_value = islands_noise.get([x, y / ISLANDS_VERTICAL_STRETCH, z]);
+        let erosion_value = islands_area.get(pos_in_biome);
+
+        Some(noise_value * erosion_value - ISLANDS_THRESHOLD - falloff)
+    }
+}
+
+// the block of a floating island, depth is the number of blocks of the island above it
+pub fn get_floating_block_at(depth: u32) -> Block {
+    if depth == 0 {
+        Block::new(GRASS_BLOCK, BlockOrientation::PositiveX)
+    } else if depth <= ISLANDS_SOIL_DEPTH {
+        Block::new(DIRT_BLOCK, BlockOrientation::PositiveX)
+    } else {
+        Block::new(STONE_BLOCK, BlockOrientation::PositiveX)
+    }
+}
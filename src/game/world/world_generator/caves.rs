@@ -0,0 +1,89 @@
+use super::biomes::biome::OCEAN_LEVEL;
+use super::generator::WorldGenNoises;
+use super::noise_layers::{CHEESE_CAVES, WORM_CAVES_A, WORM_CAVES_B};
+use noise::NoiseFn;
+
+// the blocks below this height are never carved so the world always has a floor
+pub const CAVES_FLOOR_HEIGHT: u32 = 5;
+// the blocks kept between a cave and the water of an ocean or a lake above it
+const UNDERWATER_ROOF_THICKNESS: f64 = 4.;
+
+// the caves are flattened vertically, the cheese caves are big round chambers
+// and the worm caves are long tunnels where two noises are both close to 0
+const CAVES_VERTICAL_SQUASH: f64 = 2.;
+
+// How much a biome is carved by each kind of cave. A cheese cave is where the noise
+// goes above cheese_threshold (in [-1; 1], 1 means no cheese cave), a worm cave is
+// where both worm noises are closer to 0 than worm_radius (0 means no worm cave).
+#[derive(Clone, Copy, Debug)]
+pub struct CavesSettings {
+    pub cheese_threshold: f64,
+    pub worm_radius: f64,
+}
+
+impl CavesSettings {
+    pub const NONE: Self = Self {
+        cheese_threshold: 1.,
+        worm_radius: 0.,
+    };
+
+    // the settings of the biomes blended together are blended too so the caves
+    // don't stop suddenly at the border of a biome, the weights must add up to 1
+    pub fn blend(weighted_settings: impl Iterator<Item = (Self, f64)>) -> Self {
+        let mut blended_settings = Self {
+            cheese_threshold: 0.,
+            worm_radius: 0.,
+        };
+        for (settings, weight) in weighted_settings {
+            blended_settings.cheese_threshold += settings.cheese_threshold * weight;
+            blended_settings.worm_radius += settings.worm_radius * weight;
+        }
+        blended_settings
+    }
+
+    pub fn has_caves(&self) -> bool {
+        self.cheese_threshold < 1. || self.worm_radius > 0.
+    }
+
+    // only the blocks between the floor of the world and max_cave_height can be carved
+    pub fn is_cave(
+        &self,
+        global_pos: [f64; 3],
+        max_cave_height: f64,
+        noises: &WorldGenNoises,
+    ) -> bool {
+        let [x, y, z] = global_pos;
+        if y < CAVES_FLOOR_HEIGHT as f64 || y > max_cave_height {
+            return false;
+        }
+
+        let noise_pos = [x, y * CAVES_VERTICAL_SQUASH, z];
+        if noises.get_layer(CHEESE_CAVES).get(noise_pos) > self.cheese_threshold {
+            return true;
+        }
+
+        if self.worm_radius <= 0. {
+            return false;
+        }
+        noises.get_layer(WORM_CAVES_A).get(noise_pos).abs() < self.worm_radius
+            && noises.get_layer(WORM_CAVES_B).get(noise_pos).abs() < self.worm_radius
+    }
+}
+
+// The highest block of a column that can be carved. The water is above the terrain
+// up to OCEAN_LEVEL, so a cave must stay below the terrain of the column and of the
+// columns touching it when they are under water, otherwise the cave would open into
+// the water and leave it floating over an empty cave.
+pub fn get_max_cave_height(
+    terrain_height: f64,
+    neighbors_terrain_heights: impl Iterator<Item = f64>,
+) -> f64 {
+    let mut max_cave_height = terrain_height.floor();
+    for height in std::iter::once(terrain_height).chain(neighbors_terrain_heights) {
+        let height = height.floor();
+        if height < OCEAN_LEVEL as f64 {
+            max_cave_height = max_cave_height.min(height - UNDERWATER_ROOF_THICKNESS);
+        }
+    }
+    max_cave_height
+}
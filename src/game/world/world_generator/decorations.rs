@@ -0,0 +1,135 @@
+use crate::game::world::block::{Block, BlockOrientation};
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk_renderer::blocks_resources::blocks_ids::{AIR_BLOCK, GRASS_BLOCK};
+use bevy::log::error;
+
+// the farthest a decoration goes from the column it grows on, the chunks look
+// that far into their neighbors so the decorations crossing a border are not cut
+pub const MAX_DECORATION_RADIUS: usize = 2;
+
+const TREE_MIN_TRUNK_HEIGHT: i32 = 4;
+const TREE_MAX_TRUNK_HEIGHT: i32 = 6;
+
+const LOG_BLOCK_NAME: &str = "log_block";
+const LEAVES_BLOCK_NAME: &str = "leaves_block";
+
+#[derive(Clone, Copy, Debug)]
+pub enum DecorationKind {
+    Tree,
+    Bush,
+}
+
+// density is the chance for a column of the biome to grow this decoration
+#[derive(Clone, Copy, Debug)]
+pub struct DecorationSettings {
+    pub kind: DecorationKind,
+    pub density: f64,
+}
+
+// The blocks the decorations are made of. They are only in the definitions file,
+// so their ids are the ones the BlockRegistry gave them in this world.
+#[derive(Clone, Copy, Debug)]
+pub struct DecorationBlocks {
+    log: u16,
+    leaves: u16,
+}
+
+impl DecorationBlocks {
+    // None when one of the blocks isn't registered, nothing can grow without it
+    pub fn from_registry(block_registry: &BlockRegistry) -> Option<Self> {
+        let get_block_id = |name: &str| {
+            let id = block_registry.get_block_id(name);
+            if id.is_none() {
+                error!("Unable to find the block {} of the decorations", name);
+            }
+            id
+        };
+
+        Some(Self {
+            log: get_block_id(LOG_BLOCK_NAME)?,
+            leaves: get_block_id(LEAVES_BLOCK_NAME)?,
+        })
+    }
+}
+
+// pick the decoration growing on a column from a uniformly distributed value in [0; 1[
+pub fn pick_decoration(
+    decorations: &[DecorationSettings],
+    value: f64,
+) -> Option<&DecorationSettings> {
+    let mut remaining_value = value;
+    for decoration in decorations {
+        if remaining_value < decoration.density {
+            return Some(decoration);
+        }
+        remaining_value -= decoration.density;
+    }
+    None
+}
+
+impl DecorationKind {
+    // The blocks of the decoration, relative to the first block above the ground.
+    // The hash only vary its shape, the same hash always give the same blocks.
+    pub fn get_blocks(&self, hash: u64, blocks: &DecorationBlocks) -> Vec<([i32; 3], Block)> {
+        match self {
+            DecorationKind::Tree => get_tree_blocks(hash, blocks),
+            DecorationKind::Bush => get_bush_blocks(hash, blocks),
+        }
+    }
+}
+
+// a trunk topped with two wide layers of leaves and two narrow ones
+fn get_tree_blocks(hash: u64, blocks: &DecorationBlocks) -> Vec<([i32; 3], Block)> {
+    let nb_trunk_heights = (TREE_MAX_TRUNK_HEIGHT - TREE_MIN_TRUNK_HEIGHT + 1) as u64;
+    let trunk_height = TREE_MIN_TRUNK_HEIGHT + (hash % nb_trunk_heights) as i32;
+    let leaves = Block::new(blocks.leaves, BlockOrientation::PositiveX);
+    let log = Block::new(blocks.log, BlockOrientation::PositiveY);
+
+    let mut tree_blocks = Vec::new();
+    for y in trunk_height - 2..trunk_height + 2 {
+        let radius = if y < trunk_height { 2 } else { 1 };
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                // the corners are removed so the leaves look round, some of them
+                // are kept on the wide layers so the trees don't all look the same
+                let is_corner = x.abs() == radius && z.abs() == radius;
+                let corner_bit = ((x + radius) * 3 + (z + radius) + y * 5) as u64 % 64;
+                if is_corner && (radius == 1 || (hash >> corner_bit) & 1 == 0) {
+                    continue;
+                }
+                tree_blocks.push(([x, y, z], leaves));
+            }
+        }
+    }
+    for y in 0..trunk_height {
+        tree_blocks.push(([0, y, 0], log));
+    }
+    tree_blocks
+}
+
+// a small round pile of leaves on the ground
+fn get_bush_blocks(hash: u64, blocks: &DecorationBlocks) -> Vec<([i32; 3], Block)> {
+    let leaves = Block::new(blocks.leaves, BlockOrientation::PositiveX);
+
+    let mut bush_blocks = vec![([0, 0, 0], leaves)];
+    for [x, z] in [[1, 0], [-1, 0], [0, 1], [0, -1]] {
+        bush_blocks.push(([x, 0, z], leaves));
+    }
+    if hash & 1 == 0 {
+        bush_blocks.push(([0, 1, 0], leaves));
+    }
+    bush_blocks
+}
+
+// A decoration never replace the terrain or the water, only the air. The logs also
+// replace the leaves, so the trees touching each other give the same blocks
+// whatever the order they are placed in.
+pub fn can_replace(current_block: Block, new_block: Block, blocks: &DecorationBlocks) -> bool {
+    let current_id = current_block.get_id();
+    current_id == AIR_BLOCK || (current_id == blocks.leaves && new_block.get_id() == blocks.log)
+}
+
+// the decorations only grow on the grass, not on the sand of the beaches
+pub fn can_grow_on(ground_block: Block) -> bool {
+    ground_block.get_id() == GRASS_BLOCK
+}
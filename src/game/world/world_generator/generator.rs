@@ -1,56 +1,392 @@
-use super::biomes::biome::Biome;
-use super::chunk::AbleToGenerateChunk;
-use super::{
-    biomes::{desert_biome::DesertBiome, grass_hills_biome::GrassHillsBiome},
-    chunk::Chunk,
+use super::biome_blending::{
+    BiomeBlender, WeightedBiomeCoordinates, DEFAULT_BIOME_TRANSITION_WIDTH,
 };
+use super::biomes::biome::{AbleToGenerateTerrain, Biome};
+use super::biomes::sky_islands_biome::get_floating_block_at;
+use super::caves::{get_max_cave_height, CavesSettings};
+use super::chunk::{Chunk, CHUNK_HEIGHT, CHUNK_SIZE};
+use super::decorations::{
+    can_grow_on, can_replace, pick_decoration, DecorationBlocks, DecorationSettings,
+    MAX_DECORATION_RADIUS,
+};
+use super::noise_layers::{create_noise_layers, NoiseLayer};
+use super::ores::OreVeins;
+use super::stable_hash::stable_hash;
+use crate::game::world::block::Block;
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk_renderer::blocks_resources::blocks_ids::{AIR_BLOCK, STONE_BLOCK};
 use crate::game::world::coordinates::biome_coordinates::BiomeCoordinates;
-use noise::SuperSimplex;
+use crate::game::world::coordinates::block_coords_in_chunk::BlockCoordsInChunk;
+use crate::game::world::coordinates::chunk_coordinates::ChunkCoordinates;
+use crate::game::world::coordinates::global_coordinates::GlobalCoordinates;
+use crate::game::world::world::CHUNK_SIZE_I32;
+use std::collections::HashMap;
+
+// mixed with the coordinates so the hashes of the columns differ from the ones of the biomes
+const SURFACE_BLENDING_SALT: u32 = 1;
+const DECORATION_SALT: u32 = 2;
+const DECORATION_SHAPE_SALT: u32 = 3;
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+// the terrain heights of a chunk are computed for the columns around it too, the caves
+// need the columns touching the one they carve and the decorations can grow up to
+// MAX_DECORATION_RADIUS columns away from the chunk
+const HEIGHTMAP_MARGIN: usize = MAX_DECORATION_RADIUS + 1;
+const HEIGHTMAP_SIZE: usize = CHUNK_SIZE + 2 * HEIGHTMAP_MARGIN;
 
+// The noise layers shared by every biome, each one picks the layers it needs by
+// their name (see noise_layers)
 #[derive(Clone)]
 pub struct WorldGenNoises {
-    pub super_simplex: SuperSimplex,
+    layers: HashMap<&'static str, NoiseLayer>,
 }
 
 impl WorldGenNoises {
     pub fn new(seed: u32) -> Self {
         Self {
-            super_simplex: SuperSimplex::new(seed),
+            layers: create_noise_layers(seed).into_iter().collect(),
+        }
+    }
+
+    pub fn get_layer(&self, name: &str) -> &NoiseLayer {
+        match self.layers.get(name) {
+            Some(layer) => layer,
+            None => panic!("The noise layer : {} does not exist", name),
         }
     }
 }
 
 #[derive(Clone)]
 pub struct WorldGenerator {
+    seed: u32,
     noises: WorldGenNoises,
+    ores: OreVeins,
+    // None when the blocks of the decorations aren't registered in this world
+    decoration_blocks: Option<DecorationBlocks>,
+    biome_blender: BiomeBlender,
 }
 
 impl WorldGenerator {
-    pub fn new(seed: u32) -> Self {
+    // the blocks that aren't built in are found in the registry of the world
+    pub fn new(seed: u32, block_registry: &BlockRegistry) -> Self {
+        Self::with_biome_transition_width(seed, DEFAULT_BIOME_TRANSITION_WIDTH, block_registry)
+    }
+
+    pub fn with_biome_transition_width(
+        seed: u32,
+        biome_transition_width: u32,
+        block_registry: &BlockRegistry,
+    ) -> Self {
         Self {
+            seed,
             noises: WorldGenNoises::new(seed),
+            ores: OreVeins::new(seed, block_registry),
+            decoration_blocks: DecorationBlocks::from_registry(block_registry),
+            biome_blender: BiomeBlender::new(biome_transition_width),
         }
     }
 
-    fn get_biome_at(biome_coords: &BiomeCoordinates) -> Biome {
-        let mut hasher = DefaultHasher::new();
-        biome_coords.hash(&mut hasher);
+    // the same seed always give the same biome at the same place, see stable_hash
+    fn get_biome_at(&self, biome_coords: &BiomeCoordinates) -> Biome {
+        let (x, z) = biome_coords.to_tuple();
+        Biome::from_weighted_value(stable_hash(self.seed, &[x, z]))
+    }
+
+    // The terrain is filled up to its height with the ores in its stone and the floating
+    // terrain is added above it, then the caves are carved into it and the decorations
+    // are placed on it
+    pub fn generate_chunk(&self, chunk: &mut Chunk) {
+        let chunk_coords = chunk.get_coords();
+        let terrain_heights = self.get_terrain_heights(&chunk_coords);
+
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let column_coords = BlockCoordsInChunk::new(x, 0, z);
+                let g_pos =
+                    GlobalCoordinates::from_block_coordinates(&chunk_coords, &column_coords);
+
+                let weighted_biomes = self.biome_blender.get_weighted_biomes(&g_pos);
+                let (heightmap_x, heightmap_z) = (x + HEIGHTMAP_MARGIN, z + HEIGHTMAP_MARGIN);
+                let terrain_height = terrain_heights[heightmap_x * HEIGHTMAP_SIZE + heightmap_z];
+                let surface_biome = self.get_surface_biome(&g_pos, &weighted_biomes);
+                let [global_x, _, global_z] = g_pos.to_f64_array();
+                let caves_settings = self.get_blended_caves_settings(&weighted_biomes);
+                let max_cave_height = get_max_cave_height(
+                    terrain_height,
+                    get_neighbors_terrain_heights(&terrain_heights, heightmap_x, heightmap_z),
+                );
+                let floating_blocks = self.get_floating_blocks(&g_pos, &weighted_biomes);
 
-        // replace the hard coded value by this when it will be stable
-        // mem::variant_count::<Biome>() as u64
-        match hasher.finish() % 2 {
-            0 => Biome::Desert(DesertBiome),
-            1 => Biome::GrassHills(GrassHillsBiome),
-            _ => unreachable!(),
+                for (y, floating_block) in floating_blocks.into_iter().enumerate() {
+                    let mut block = surface_biome.get_block_at(y as u32, terrain_height);
+                    if let Some(floating_block) = floating_block {
+                        if block.get_id() == AIR_BLOCK {
+                            block = floating_block;
+                        }
+                    }
+                    let global_pos = [global_x, y as f64, global_z];
+                    if block.get_id() == STONE_BLOCK {
+                        if let Some(ore) = self.ores.get_ore_at(global_pos) {
+                            block = ore;
+                        }
+                    }
+                    if caves_settings.has_caves()
+                        && caves_settings.is_cave(global_pos, max_cave_height, &self.noises)
+                    {
+                        block = Block::default();
+                    }
+                    let block_index = BlockCoordsInChunk::new(x, y, z).get_block_index();
+                    chunk.set_block(block_index, block);
+                }
+            }
         }
+
+        self.decorate_chunk(chunk, &terrain_heights);
+        chunk.blocks.optimize_sections();
     }
 
-    pub fn generate_chunk(&self, chunk: &mut Chunk) {
-        let biome_coords = BiomeCoordinates::from_chunk_coords(&chunk.get_coords());
-        let biome = Self::get_biome_at(&biome_coords);
-        biome.generate_chunk(chunk, self.noises.clone());
+    // The decorations growing on the columns of the chunk and on the columns around it
+    // are all computed, and only their blocks inside the chunk are placed. A tree
+    // crossing a border is computed the same way by both chunks so it is never cut.
+    fn decorate_chunk(&self, chunk: &mut Chunk, terrain_heights: &[f64]) {
+        let Some(decoration_blocks) = &self.decoration_blocks else {
+            return;
+        };
+        let chunk_coords = chunk.get_coords();
+        let radius = MAX_DECORATION_RADIUS as i32;
+
+        for x in -radius..CHUNK_SIZE_I32 + radius {
+            for z in -radius..CHUNK_SIZE_I32 + radius {
+                let Some((ground_y, decoration, shape_hash)) =
+                    self.get_decoration_at(&chunk_coords, x, z, terrain_heights)
+                else {
+                    continue;
+                };
+
+                for ([offset_x, offset_y, offset_z], block) in
+                    decoration.kind.get_blocks(shape_hash, decoration_blocks)
+                {
+                    let (block_x, block_y, block_z) =
+                        (x + offset_x, ground_y + 1 + offset_y, z + offset_z);
+                    if !(0..CHUNK_SIZE_I32).contains(&block_x)
+                        || !(0..CHUNK_HEIGHT as i32).contains(&block_y)
+                        || !(0..CHUNK_SIZE_I32).contains(&block_z)
+                    {
+                        continue;
+                    }
+
+                    let block_index = BlockCoordsInChunk::new(
+                        block_x as usize,
+                        block_y as usize,
+                        block_z as usize,
+                    )
+                    .get_block_index();
+                    if can_replace(chunk.get_block(block_index), block, decoration_blocks) {
+                        chunk.set_block(block_index, block);
+                    }
+                }
+            }
+        }
     }
+
+    // The decoration growing on the column at x, z relative to the chunk, with the height
+    // of its ground and the hash giving its shape. It only depends on the seed and the
+    // column so every chunk reaching the column find the same decoration.
+    fn get_decoration_at(
+        &self,
+        chunk_coords: &ChunkCoordinates,
+        x: i32,
+        z: i32,
+        terrain_heights: &[f64],
+    ) -> Option<(i32, &'static DecorationSettings, u64)> {
+        let g_pos = get_column_global_coordinates(chunk_coords, x, z);
+        let weighted_biomes = self.biome_blender.get_weighted_biomes(&g_pos);
+        let surface_biome = self.get_surface_biome(&g_pos, &weighted_biomes);
+
+        let hash = stable_hash(self.seed, &[g_pos.get_x(), g_pos.get_z(), DECORATION_SALT]);
+        let decoration = pick_decoration(
+            surface_biome.get_decorations(),
+            hash as f64 / u64::MAX as f64,
+        )?;
+
+        let heightmap_x = (x + HEIGHTMAP_MARGIN as i32) as usize;
+        let heightmap_z = (z + HEIGHTMAP_MARGIN as i32) as usize;
+        let terrain_height = terrain_heights[heightmap_x * HEIGHTMAP_SIZE + heightmap_z];
+        let ground_y = terrain_height.floor();
+        if ground_y < 0.
+            || !can_grow_on(surface_biome.get_block_at(ground_y as u32, terrain_height))
+        {
+            return None;
+        }
+
+        // nothing grows over the hole of a cave opening on the surface
+        let caves_settings = self.get_blended_caves_settings(&weighted_biomes);
+        let max_cave_height = get_max_cave_height(
+            terrain_height,
+            get_neighbors_terrain_heights(terrain_heights, heightmap_x, heightmap_z),
+        );
+        let [global_x, _, global_z] = g_pos.to_f64_array();
+        if caves_settings.has_caves()
+            && caves_settings.is_cave(
+                [global_x, ground_y, global_z],
+                max_cave_height,
+                &self.noises,
+            )
+        {
+            return None;
+        }
+
+        let shape_hash = stable_hash(
+            self.seed,
+            &[g_pos.get_x(), g_pos.get_z(), DECORATION_SHAPE_SALT],
+        );
+        Some((ground_y as i32, decoration, shape_hash))
+    }
+
+    // the heights of the columns of the chunk and of the columns around it, indexed by
+    // (x + HEIGHTMAP_MARGIN) * HEIGHTMAP_SIZE + z + HEIGHTMAP_MARGIN
+    fn get_terrain_heights(&self, chunk_coords: &ChunkCoordinates) -> Vec<f64> {
+        let margin = HEIGHTMAP_MARGIN as i32;
+        let mut terrain_heights = Vec::with_capacity(HEIGHTMAP_SIZE * HEIGHTMAP_SIZE);
+
+        for x in -margin..CHUNK_SIZE_I32 + margin {
+            for z in -margin..CHUNK_SIZE_I32 + margin {
+                let g_pos = get_column_global_coordinates(chunk_coords, x, z);
+
+                let weighted_biomes = self.biome_blender.get_weighted_biomes(&g_pos);
+                terrain_heights.push(self.get_blended_terrain_height(&g_pos, &weighted_biomes));
+            }
+        }
+        terrain_heights
+    }
+
+    // The blocks of the floating terrain of the column, from the top of the column so
+    // the first blocks of each floating island are its soil. The floating densities
+    // of the biomes are blended like their terrain heights, the biomes with nothing
+    // floating count as empty.
+    fn get_floating_blocks(
+        &self,
+        g_pos: &GlobalCoordinates,
+        weighted_biomes: &[WeightedBiomeCoordinates],
+    ) -> Vec<Option<Block>> {
+        let biomes: Vec<(Biome, [f64; 2], f64)> = weighted_biomes
+            .iter()
+            .map(|weighted_biome| {
+                (
+                    self.get_biome_at(&weighted_biome.biome_coords),
+                    weighted_biome.biome_coords.get_pos_in_biome(g_pos),
+                    weighted_biome.weight,
+                )
+            })
+            .collect();
+        let [global_x, _, global_z] = g_pos.to_f64_array();
+
+        let mut floating_blocks = vec![None; CHUNK_HEIGHT];
+        let mut depth = 0;
+        for y in (0..CHUNK_HEIGHT).rev() {
+            let global_pos = [global_x, y as f64, global_z];
+            let mut has_floating_terrain = false;
+            let mut density = 0.;
+            for (biome, pos_in_biome, weight) in biomes.iter() {
+                let biome_density =
+                    biome.get_floating_density(global_pos, *pos_in_biome, &self.noises);
+                has_floating_terrain |= biome_density.is_some();
+                density += biome_density.unwrap_or(-1.) * weight;
+            }
+            // none of the biomes of the column has floating terrain
+            if !has_floating_terrain {
+                break;
+            }
+
+            if density > 0. {
+                floating_blocks[y] = Some(get_floating_block_at(depth));
+                depth += 1;
+            } else {
+                depth = 0;
+            }
+        }
+        floating_blocks
+    }
+
+    fn get_blended_caves_settings(
+        &self,
+        weighted_biomes: &[WeightedBiomeCoordinates],
+    ) -> CavesSettings {
+        CavesSettings::blend(weighted_biomes.iter().map(|weighted_biome| {
+            let biome = self.get_biome_at(&weighted_biome.biome_coords);
+            (biome.get_caves_settings(), weighted_biome.weight)
+        }))
+    }
+
+    fn get_blended_terrain_height(
+        &self,
+        g_pos: &GlobalCoordinates,
+        weighted_biomes: &[WeightedBiomeCoordinates],
+    ) -> f64 {
+        let mut terrain_height = 0.;
+        for weighted_biome in weighted_biomes {
+            let biome = self.get_biome_at(&weighted_biome.biome_coords);
+            let pos_in_biome = weighted_biome.biome_coords.get_pos_in_biome(g_pos);
+            let biome_height =
+                biome.get_terrain_height(g_pos.to_2d_f64_array(), pos_in_biome, &self.noises);
+            terrain_height += biome_height * weighted_biome.weight;
+        }
+        terrain_height
+    }
+
+    // blocks can't be interpolated, instead each column pick one of the biomes
+    // with a probability equal to its weight, so the transition is dithered
+    fn get_surface_biome(
+        &self,
+        g_pos: &GlobalCoordinates,
+        weighted_biomes: &[WeightedBiomeCoordinates],
+    ) -> Biome {
+        let hash = stable_hash(
+            self.seed,
+            &[g_pos.get_x(), g_pos.get_z(), SURFACE_BLENDING_SALT],
+        );
+        let mut remaining_weight = hash as f64 / u64::MAX as f64;
+
+        for weighted_biome in weighted_biomes {
+            if remaining_weight < weighted_biome.weight {
+                return self.get_biome_at(&weighted_biome.biome_coords);
+            }
+            remaining_weight -= weighted_biome.weight;
+        }
+
+        // only reached because of rounding errors
+        let last_biome = weighted_biomes.last().unwrap();
+        self.get_biome_at(&last_biome.biome_coords)
+    }
+}
+
+// the column at x, z relative to the chunk, the columns around the chunk are in its neighbors
+fn get_column_global_coordinates(
+    chunk_coords: &ChunkCoordinates,
+    x: i32,
+    z: i32,
+) -> GlobalCoordinates {
+    let (chunk_x, chunk_y, chunk_z) = chunk_coords.to_tuple();
+    let column_chunk_coords = ChunkCoordinates::new(
+        chunk_x + x.div_euclid(CHUNK_SIZE_I32) * CHUNK_SIZE_I32,
+        chunk_y,
+        chunk_z + z.div_euclid(CHUNK_SIZE_I32) * CHUNK_SIZE_I32,
+    );
+    let column_coords = BlockCoordsInChunk::new(
+        x.rem_euclid(CHUNK_SIZE_I32) as usize,
+        0,
+        z.rem_euclid(CHUNK_SIZE_I32) as usize,
+    );
+    GlobalCoordinates::from_block_coordinates(&column_chunk_coords, &column_coords)
+}
+
+// the 8 columns touching the column at x, z in the terrain heights
+fn get_neighbors_terrain_heights(
+    terrain_heights: &[f64],
+    x: usize,
+    z: usize,
+) -> impl Iterator<Item = f64> + '_ {
+    (x - 1..=x + 1)
+        .flat_map(move |neighbor_x| (z - 1..=z + 1).map(move |neighbor_z| (neighbor_x, neighbor_z)))
+        .filter(move |neighbor| *neighbor != (x, z))
+        .map(|(neighbor_x, neighbor_z)| terrain_heights[neighbor_x * HEIGHTMAP_SIZE + neighbor_z])
 }
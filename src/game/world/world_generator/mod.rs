@@ -1,3 +1,9 @@
 use super::chunk;
+pub mod biome_blending;
 pub mod biomes;
+pub mod caves;
+pub mod decorations;
 pub mod generator;
+pub mod noise_layers;
+pub mod ores;
+pub mod stable_hash;
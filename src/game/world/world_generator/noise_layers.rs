@@ -0,0 +1,287 @@
+use super::stable_hash::stable_hash;
+use noise::{NoiseFn, SuperSimplex};
+
+// the names of the noise layers, the biomes get them from WorldGenNoises::get_layer
+pub const CONTINENTALNESS: &str = "continentalness";
+pub const EROSION: &str = "erosion";
+pub const DETAIL: &str = "detail";
+pub const TEMPERATURE: &str = "temperature";
+pub const DUNES: &str = "dunes";
+pub const SKY_ISLANDS: &str = "sky_islands";
+pub const CHEESE_CAVES: &str = "cheese_caves";
+pub const WORM_CAVES_A: &str = "worm_caves_a";
+pub const WORM_CAVES_B: &str = "worm_caves_b";
+
+// mixed with the seed of a layer so its warping doesn't use the noises of its octaves
+const DOMAIN_WARP_SALT: u32 = 1;
+// a 2D layer only use the first two
+const NB_WARP_AXIS: usize = 3;
+
+// Every layer of the world generation, the frequencies are in cycles per block.
+// Changing one of them change the terrain of every existing world.
+const NOISE_LAYERS: [(&str, NoiseLayerSettings); 9] = [
+    // where the land raise from the oceans, warped so the coasts are not round
+    (
+        CONTINENTALNESS,
+        NoiseLayerSettings {
+            fractal: FractalKind::Fbm,
+            frequency: 1. / 400.,
+            octaves: 4,
+            lacunarity: 2.,
+            persistence: 0.45,
+            domain_warp: Some(DomainWarpSettings {
+                frequency: 1. / 300.,
+                strength: 60.,
+            }),
+        },
+    ),
+    // low where the terrain is flat, high where it is rough
+    (
+        EROSION,
+        NoiseLayerSettings {
+            fractal: FractalKind::Fbm,
+            frequency: 1. / 300.,
+            octaves: 3,
+            lacunarity: 2.,
+            persistence: 0.5,
+            domain_warp: None,
+        },
+    ),
+    // the small bumps over the shape of the terrain
+    (
+        DETAIL,
+        NoiseLayerSettings {
+            fractal: FractalKind::Fbm,
+            frequency: 1. / 48.,
+            octaves: 3,
+            lacunarity: 2.,
+            persistence: 0.5,
+            domain_warp: None,
+        },
+    ),
+    (
+        TEMPERATURE,
+        NoiseLayerSettings {
+            fractal: FractalKind::Fbm,
+            frequency: 1. / 1000.,
+            octaves: 2,
+            lacunarity: 2.,
+            persistence: 0.5,
+            domain_warp: None,
+        },
+    ),
+    // sharp crests, stretched by the desert to look pushed by the wind
+    (
+        DUNES,
+        NoiseLayerSettings {
+            fractal: FractalKind::Ridged,
+            frequency: 1. / 150.,
+            octaves: 3,
+            lacunarity: 2.,
+            persistence: 0.4,
+            domain_warp: Some(DomainWarpSettings {
+                frequency: 1. / 200.,
+                strength: 30.,
+            }),
+        },
+    ),
+    // a 3D layer, the sky islands biome makes its floating islands where it is high
+    (
+        SKY_ISLANDS,
+        NoiseLayerSettings {
+            fractal: FractalKind::Fbm,
+            frequency: 1. / 96.,
+            octaves: 3,
+            lacunarity: 2.,
+            persistence: 0.5,
+            domain_warp: Some(DomainWarpSettings {
+                frequency: 1. / 128.,
+                strength: 24.,
+            }),
+        },
+    ),
+    // the caves use a single octave, their thresholds are tuned for it (see caves)
+    (
+        CHEESE_CAVES,
+        NoiseLayerSettings {
+            fractal: FractalKind::Fbm,
+            frequency: 1. / 64.,
+            octaves: 1,
+            lacunarity: 2.,
+            persistence: 0.5,
+            domain_warp: None,
+        },
+    ),
+    (
+        WORM_CAVES_A,
+        NoiseLayerSettings {
+            fractal: FractalKind::Fbm,
+            frequency: 1. / 96.,
+            octaves: 1,
+            lacunarity: 2.,
+            persistence: 0.5,
+            domain_warp: None,
+        },
+    ),
+    (
+        WORM_CAVES_B,
+        NoiseLayerSettings {
+            fractal: FractalKind::Fbm,
+            frequency: 1. / 96.,
+            octaves: 1,
+            lacunarity: 2.,
+            persistence: 0.5,
+            domain_warp: None,
+        },
+    ),
+];
+
+#[derive(Clone, Copy, Debug)]
+pub enum FractalKind {
+    // the octaves are added together, the terrain rolls smoothly
+    Fbm,
+    // the octaves are folded around 0 and sharpen each other, like mountain crests
+    Ridged,
+}
+
+// the position is moved by up to strength blocks along each axis before the
+// layer is sampled, following another noise of the given frequency
+#[derive(Clone, Copy, Debug)]
+pub struct DomainWarpSettings {
+    pub frequency: f64,
+    pub strength: f64,
+}
+
+// Each octave has lacunarity times the frequency of the previous one and
+// persistence times its amplitude.
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseLayerSettings {
+    pub fractal: FractalKind,
+    pub frequency: f64,
+    pub octaves: usize,
+    pub lacunarity: f64,
+    pub persistence: f64,
+    pub domain_warp: Option<DomainWarpSettings>,
+}
+
+// A fractal noise made of several octaves of SuperSimplex, its values are in [-1; 1]
+#[derive(Clone)]
+pub struct NoiseLayer {
+    settings: NoiseLayerSettings,
+    octaves: Vec<SuperSimplex>,
+    warp_noises: Vec<SuperSimplex>,
+}
+
+impl NoiseLayer {
+    pub fn new(seed: u32, settings: NoiseLayerSettings) -> Self {
+        let octaves = (0..settings.octaves.max(1) as u32)
+            .map(|octave| SuperSimplex::new(stable_hash(seed, &[octave]) as u32))
+            .collect();
+        let warp_noises = (0..NB_WARP_AXIS as u32)
+            .map(|axis| SuperSimplex::new(stable_hash(seed, &[DOMAIN_WARP_SALT, axis]) as u32))
+            .collect();
+
+        Self {
+            settings,
+            octaves,
+            warp_noises,
+        }
+    }
+
+    fn get_value<const DIM: usize>(&self, point: [f64; DIM]) -> f64
+    where
+        SuperSimplex: NoiseFn<f64, DIM>,
+    {
+        let point = self.warp(point);
+        match self.settings.fractal {
+            FractalKind::Fbm => self.get_fbm(point),
+            FractalKind::Ridged => self.get_ridged(point),
+        }
+    }
+
+    fn warp<const DIM: usize>(&self, point: [f64; DIM]) -> [f64; DIM]
+    where
+        SuperSimplex: NoiseFn<f64, DIM>,
+    {
+        let Some(domain_warp) = self.settings.domain_warp else {
+            return point;
+        };
+
+        let warp_point = point.map(|coord| coord * domain_warp.frequency);
+        let mut warped_point = point;
+        for (coord, warp_noise) in warped_point.iter_mut().zip(self.warp_noises.iter()) {
+            *coord += warp_noise.get(warp_point) * domain_warp.strength;
+        }
+        warped_point
+    }
+
+    // divided by the sum of the amplitudes so it stays in [-1; 1]
+    fn get_fbm<const DIM: usize>(&self, point: [f64; DIM]) -> f64
+    where
+        SuperSimplex: NoiseFn<f64, DIM>,
+    {
+        let mut point = point.map(|coord| coord * self.settings.frequency);
+        let mut amplitude = 1.;
+        let mut total_amplitude = 0.;
+        let mut value = 0.;
+
+        for octave in self.octaves.iter() {
+            value += octave.get(point) * amplitude;
+            total_amplitude += amplitude;
+            amplitude *= self.settings.persistence;
+            point = point.map(|coord| coord * self.settings.lacunarity);
+        }
+        value / total_amplitude
+    }
+
+    // each octave is only added where the previous ones are close to a crest
+    fn get_ridged<const DIM: usize>(&self, point: [f64; DIM]) -> f64
+    where
+        SuperSimplex: NoiseFn<f64, DIM>,
+    {
+        let mut point = point.map(|coord| coord * self.settings.frequency);
+        let mut amplitude = 1.;
+        let mut total_amplitude = 0.;
+        let mut weight = 1.;
+        let mut value = 0.;
+
+        for octave in self.octaves.iter() {
+            let signal = (1. - octave.get(point).abs()).powi(2) * weight;
+            weight = signal.clamp(0., 1.);
+            value += signal * amplitude;
+            total_amplitude += amplitude;
+            amplitude *= self.settings.persistence;
+            point = point.map(|coord| coord * self.settings.lacunarity);
+        }
+        (value / total_amplitude) * 2. - 1.
+    }
+}
+
+impl NoiseFn<f64, 2> for NoiseLayer {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        self.get_value(point)
+    }
+}
+
+impl NoiseFn<f64, 3> for NoiseLayer {
+    fn get(&self, point: [f64; 3]) -> f64 {
+        self.get_value(point)
+    }
+}
+
+// every layer has its own seed taken from the seed of the world and its name,
+// so adding a layer doesn't change the others
+pub fn create_noise_layers(world_seed: u32) -> Vec<(&'static str, NoiseLayer)> {
+    NOISE_LAYERS
+        .iter()
+        .map(|(name, settings)| {
+            let layer_seed = get_layer_seed(world_seed, name);
+            (*name, NoiseLayer::new(layer_seed, *settings))
+        })
+        .collect()
+}
+
+pub fn get_layer_seed(world_seed: u32, name: &str) -> u32 {
+    let name_values: Vec<u32> = name.bytes().map(u32::from).collect();
+    stable_hash(world_seed, &name_values) as u32
+}
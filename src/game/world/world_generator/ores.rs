@@ -0,0 +1,99 @@
+use super::noise_layers::{get_layer_seed, FractalKind, NoiseLayer, NoiseLayerSettings};
+use crate::game::world::block::{Block, BlockOrientation};
+use crate::game::world::block_registry::BlockRegistry;
+use bevy::log::error;
+use noise::NoiseFn;
+
+// The ores are placed in the stone between min_height and max_height. A vein is where
+// the noise of the ore goes above rarity (in [-1; 1], the higher the rarer) and it is
+// about vein_size blocks wide. When two veins meet the first ore of the list is kept.
+// The ores are only in the definitions file, their block is found by its name.
+#[derive(Clone, Copy, Debug)]
+pub struct OreSettings {
+    pub name: &'static str,
+    pub block_name: &'static str,
+    pub min_height: u32,
+    pub max_height: u32,
+    pub vein_size: f64,
+    pub rarity: f64,
+}
+
+// with a rarity of 0.7 about 1% of the stone is in a vein
+const ORES: [OreSettings; 3] = [
+    OreSettings {
+        name: "gold_ore",
+        block_name: "gold_ore_block",
+        min_height: 5,
+        max_height: 32,
+        vein_size: 2.,
+        rarity: 0.78,
+    },
+    OreSettings {
+        name: "iron_ore",
+        block_name: "iron_ore_block",
+        min_height: 5,
+        max_height: 64,
+        vein_size: 3.,
+        rarity: 0.72,
+    },
+    OreSettings {
+        name: "coal_ore",
+        block_name: "coal_ore_block",
+        min_height: 5,
+        max_height: 128,
+        vein_size: 4.,
+        rarity: 0.65,
+    },
+];
+
+// the noise is above the rarity on about a quarter of its period
+const VEIN_SIZE_TO_PERIOD: f64 = 4.;
+
+#[derive(Clone)]
+pub struct OreVeins {
+    veins: Vec<(OreSettings, Block, NoiseLayer)>,
+}
+
+impl OreVeins {
+    // Each ore has its own noise seeded from the seed of the world and its name.
+    // The ores whose block isn't registered in the world are not placed.
+    pub fn new(world_seed: u32, block_registry: &BlockRegistry) -> Self {
+        let veins = ORES
+            .iter()
+            .filter_map(|ore| {
+                let Some(block_id) = block_registry.get_block_id(ore.block_name) else {
+                    error!(
+                        "Unable to find the block {} of the ore {}",
+                        ore.block_name, ore.name
+                    );
+                    return None;
+                };
+                let settings = NoiseLayerSettings {
+                    fractal: FractalKind::Fbm,
+                    frequency: 1. / (ore.vein_size * VEIN_SIZE_TO_PERIOD),
+                    octaves: 1,
+                    lacunarity: 2.,
+                    persistence: 0.5,
+                    domain_warp: None,
+                };
+                Some((
+                    *ore,
+                    Block::new(block_id, BlockOrientation::PositiveX),
+                    NoiseLayer::new(get_layer_seed(world_seed, ore.name), settings),
+                ))
+            })
+            .collect();
+
+        Self { veins }
+    }
+
+    // the ore replacing the stone at this position, if it is in a vein
+    pub fn get_ore_at(&self, global_pos: [f64; 3]) -> Option<Block> {
+        let y = global_pos[1];
+        self.veins
+            .iter()
+            .filter(|(ore, _, _)| y >= ore.min_height as f64 && y <= ore.max_height as f64)
+            .find(|(ore, _, noise)| noise.get(global_pos) > ore.rarity)
+            .map(|(_, block, _)| *block)
+    }
+}
@@ -0,0 +1,25 @@
+// A small hash used by the world generation, everything it produce must only
+// depend on the seed and the coordinates so a world look the same on every
+// machine and with every version of Rust (unlike std DefaultHasher).
+//
+// Each value is mixed in with the SplitMix64 finalizer :
+// z = (z ^ (z >> 30)) * 0xBF58476D1CE4E5B9
+// z = (z ^ (z >> 27)) * 0x94D049BB133111EB
+// z = z ^ (z >> 31)
+// Changing anything here change every existing world, don't.
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+fn split_mix_64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(GOLDEN_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub fn stable_hash(seed: u32, values: &[u32]) -> u64 {
+    let mut hash = split_mix_64(seed as u64);
+    for value in values {
+        hash = split_mix_64(hash ^ *value as u64);
+    }
+    hash
+}